@@ -0,0 +1,191 @@
+use crate::queue;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// A `tracing` layer that sends spans and events to Dbug Desktop
+///
+/// Each span is sent when it opens and again, with its duration, when it closes, and the app
+/// shows events inside the span they happened in. Sending happens on the same background thread
+/// as `dbug!`.
+///
+/// ```no_run
+/// use tracing_subscriber::prelude::*;
+///
+/// tracing_subscriber::registry().with(dbug_client::DbugLayer::new()).init();
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DbugLayer {
+    _private: (),
+}
+
+impl DbugLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// What the layer tracks for an open span, kept in the span's extensions
+struct SpanState {
+    /// ID the span is sent with, so its closing update replaces the opening one
+    id: String,
+    parent: Option<String>,
+    fields: Map<String, Value>,
+    opened: Instant,
+    /// Time spent entered, excluding time the span was idle, e.g. an awaiting future
+    busy: Duration,
+    entered: Option<Instant>,
+}
+
+/// A span ID that is unique across processes, as `tracing`'s own IDs are reused
+fn next_span_id() -> String {
+    static PREFIX: OnceLock<String> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+
+    let prefix = PREFIX.get_or_init(|| {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        format!("span-{:x}-{:x}", std::process::id(), started.as_millis())
+    });
+    format!("{prefix}-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// The envelope options shared by spans and events
+fn options(kind: &str, metadata: &Metadata<'_>) -> Map<String, Value> {
+    let mut options = Map::new();
+    options.insert("type".to_string(), kind.into());
+    options.insert("level".to_string(), metadata.level().as_str().to_lowercase().into());
+    options.insert("target".to_string(), metadata.target().into());
+    if let (Some(file), Some(line)) = (metadata.file(), metadata.line()) {
+        options.insert("file".to_string(), file.into());
+        options.insert("line".to_string(), line.into());
+    }
+    options
+}
+
+fn send_span(metadata: &Metadata<'_>, state: &SpanState, closed: bool) {
+    let mut options = options("span", metadata);
+    options.insert("id".to_string(), state.id.clone().into());
+    options.insert("name".to_string(), metadata.name().into());
+    if let Some(parent) = &state.parent {
+        options.insert("parent".to_string(), parent.clone().into());
+    }
+
+    let mut data = json!({ "fields": state.fields });
+    if closed {
+        data["duration_ms"] = (state.opened.elapsed().as_secs_f64() * 1000.0).into();
+        data["busy_ms"] = (state.busy.as_secs_f64() * 1000.0).into();
+    }
+    queue(&json!({ "$dbug": options, "data": data }));
+}
+
+/// Collects a span's or event's fields as JSON
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+impl<S> Layer<S> for DbugLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span
+            .parent()
+            .and_then(|parent| parent.extensions().get::<SpanState>().map(|state| state.id.clone()));
+
+        let mut fields = Map::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let state = SpanState {
+            id: next_span_id(),
+            parent,
+            fields,
+            opened: Instant::now(),
+            busy: Duration::ZERO,
+            entered: None,
+        };
+        send_span(span.metadata(), &state, false);
+        span.extensions_mut().insert(state);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState>() {
+                values.record(&mut FieldVisitor(&mut state.fields));
+            }
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState>() {
+                state.entered.get_or_insert_with(Instant::now);
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState>() {
+                if let Some(entered) = state.entered.take() {
+                    state.busy += entered.elapsed();
+                }
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id) {
+            if let Some(state) = span.extensions_mut().remove::<SpanState>() {
+                send_span(span.metadata(), &state, true);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        // Events are shown with the log view, inside the span they happened in
+        let mut options = options("log", event.metadata());
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(state) = span.extensions().get::<SpanState>() {
+                options.insert("span".to_string(), state.id.clone().into());
+            }
+        }
+
+        let mut fields = Map::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        queue(&json!({ "$dbug": options, "data": fields }));
+    }
+}
@@ -0,0 +1,309 @@
+//! Sends values to Dbug Desktop from Rust code
+//!
+//! `dbug!` works like `dbg!`: it returns its argument and sends it to the app, tagged with the file,
+//! line and module it was called from. Sending happens on a background thread, so the caller never
+//! waits for the app, and nothing is sent when the app is not running.
+//!
+//! ```no_run
+//! use dbug_client::dbug;
+//!
+//! let user = dbug!(serde_json::json!({ "name": "Ada", "admin": true }));
+//! let (a, b) = dbug!(1 + 1, "two");
+//! ```
+//!
+//! `dbug!` is compiled out of release builds unless the `release` feature is enabled.
+//!
+//! With the `tracing` feature, [`DbugLayer`] sends spans and events from the `tracing` crate too,
+//! and with the `log` feature, [`DbugLogger`] sends records from the `log` crate.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Host the app listens on unless configured otherwise
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// Port the app listens on unless configured otherwise
+pub const DEFAULT_PORT: u16 = 53821;
+
+// How long to wait for the app to accept a connection before dropping a payload
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+
+// How long a connected request may take
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Payloads queued but not yet sent, for `flush`
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "tracing")]
+mod layer;
+
+#[cfg(feature = "log")]
+mod logger;
+
+#[cfg(feature = "tracing")]
+pub use layer::DbugLayer;
+
+#[cfg(feature = "log")]
+pub use logger::DbugLogger;
+
+/// Where a `dbug!` call was made
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub file: &'static str,
+    pub line: u32,
+    pub module: &'static str,
+    /// The expression passed to `dbug!`, as written
+    pub expr: &'static str,
+}
+
+/// Sends a value to Dbug Desktop and returns it, like `dbg!`
+///
+/// Any `Serialize` value works; several values are sent separately and returned as a tuple.
+#[macro_export]
+macro_rules! dbug {
+    ($value:expr $(,)?) => {
+        // `match` keeps temporaries alive, as in `dbg!`
+        match $value {
+            value => {
+                if $crate::is_enabled(cfg!(debug_assertions)) {
+                    $crate::send(
+                        &value,
+                        $crate::Location {
+                            file: file!(),
+                            line: line!(),
+                            module: module_path!(),
+                            expr: stringify!($value),
+                        },
+                    );
+                }
+                value
+            }
+        }
+    };
+    ($($value:expr),+ $(,)?) => {
+        ($($crate::dbug!($value)),+,)
+    };
+}
+
+/// Whether `dbug!` sends anything: in debug builds of the calling crate, or with the `release` feature
+#[doc(hidden)]
+pub const fn is_enabled(debug_assertions: bool) -> bool {
+    debug_assertions || cfg!(feature = "release")
+}
+
+/// Queues a value to be sent to the app with where it came from, without waiting
+pub fn send<T: Serialize + ?Sized>(value: &T, location: Location) {
+    queue(&envelope(value, location));
+}
+
+/// Wraps a value in the typed envelope the app reads the call site from
+fn envelope<T: Serialize + ?Sized>(value: &T, location: Location) -> Value {
+    let data = serde_json::to_value(value).unwrap_or_else(|e| Value::String(format!("Failed to serialize value: {e}")));
+    json!({
+        "$dbug": {
+            "file": location.file,
+            "line": location.line,
+            "module": location.module,
+            "expr": location.expr,
+        },
+        "data": data,
+    })
+}
+
+/// Hands a payload to the background thread
+pub(crate) fn queue(body: &Value) {
+    let Some(worker) = worker() else {
+        return;
+    };
+    PENDING.fetch_add(1, Ordering::SeqCst);
+    if worker.send(body.to_string()).is_err() {
+        PENDING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Waits up to `timeout` for queued payloads to be sent, e.g. before a short-lived program exits
+///
+/// Returns whether everything was sent in time.
+pub fn flush(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while PENDING.load(Ordering::SeqCst) > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    true
+}
+
+/// The app's host and port: `DBUG_HOST` and `DBUG_PORT`, then the app's saved settings, then the defaults
+pub fn server_address() -> (String, u16) {
+    let config = dirs::home_dir()
+        .and_then(|home| std::fs::read_to_string(home.join(".dbug_desktop").join("config.json")).ok())
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+    resolve_address(std::env::var("DBUG_HOST").ok(), std::env::var("DBUG_PORT").ok(), config.as_ref())
+}
+
+/// Picks the host and port from the environment variables' values, the saved settings and the defaults
+fn resolve_address(host_var: Option<String>, port_var: Option<String>, config: Option<&Value>) -> (String, u16) {
+    let host = host_var
+        .or_else(|| Some(config?.get("server_host")?.as_str()?.to_string()))
+        .unwrap_or_else(|| DEFAULT_HOST.to_string());
+    let port = port_var
+        .and_then(|port| port.parse().ok())
+        .or_else(|| config?.get("server_port")?.as_u64()?.try_into().ok())
+        .unwrap_or(DEFAULT_PORT);
+    (host, port)
+}
+
+/// The background thread's queue, started on first use
+fn worker() -> Option<&'static Sender<String>> {
+    static WORKER: OnceLock<Option<Sender<String>>> = OnceLock::new();
+
+    WORKER
+        .get_or_init(|| {
+            let (sender, bodies) = mpsc::channel::<String>();
+            let (host, port) = server_address();
+            thread::Builder::new()
+                .name("dbug".to_string())
+                .spawn(move || {
+                    for body in bodies {
+                        // The app may not be running; payloads are dropped rather than retried
+                        let _ = post(&host, port, &body);
+                        PENDING.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+                .ok()?;
+            Some(sender)
+        })
+        .as_ref()
+}
+
+fn post(host: &str, port: u16, body: &str) -> io::Result<()> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{host} did not resolve")))?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    write!(
+        stream,
+        "POST / HTTP/1.1\r\nHost: {host}:{port}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    // Read the reply so the connection is not reset before the server has handled the request
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    const LOCATION: Location = Location {
+        file: "src/main.rs",
+        line: 42,
+        module: "app::jobs",
+        expr: "user",
+    };
+
+    #[test]
+    fn environment_comes_before_saved_settings() {
+        let config = json!({ "server_host": "10.0.0.2", "server_port": 6000 });
+        let address = resolve_address(Some("10.0.0.1".to_string()), Some("5000".to_string()), Some(&config));
+        assert_eq!(address, ("10.0.0.1".to_string(), 5000));
+    }
+
+    #[test]
+    fn saved_settings_come_before_defaults() {
+        let config = json!({ "server_host": "10.0.0.2", "server_port": 6000 });
+        assert_eq!(resolve_address(None, None, Some(&config)), ("10.0.0.2".to_string(), 6000));
+        // An unusable port variable is skipped rather than trusted
+        assert_eq!(resolve_address(None, Some("not a port".to_string()), Some(&config)).1, 6000);
+    }
+
+    #[test]
+    fn defaults_fill_in_what_is_missing_or_invalid() {
+        assert_eq!(resolve_address(None, None, None), (DEFAULT_HOST.to_string(), DEFAULT_PORT));
+        let config = json!({ "server_host": 7, "server_port": 70000 });
+        assert_eq!(resolve_address(None, Some("70000".to_string()), Some(&config)), (DEFAULT_HOST.to_string(), DEFAULT_PORT));
+    }
+
+    #[test]
+    fn envelope_carries_the_call_site() {
+        let body = envelope(&("Ada", 36), LOCATION);
+        assert_eq!(
+            body,
+            json!({
+                "$dbug": { "file": "src/main.rs", "line": 42, "module": "app::jobs", "expr": "user" },
+                "data": ["Ada", 36],
+            })
+        );
+    }
+
+    #[test]
+    fn unserializable_values_are_sent_as_the_error() {
+        let mut map = std::collections::HashMap::new();
+        map.insert((1, 2), "tuple keys are not JSON object keys");
+        let body = envelope(&map, LOCATION);
+        assert!(body["data"].as_str().is_some_and(|data| data.starts_with("Failed to serialize value:")));
+    }
+
+    #[test]
+    fn post_sends_one_http_request() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line.trim_end().to_string());
+            }
+            let length: usize = head
+                .iter()
+                .find_map(|header| header.strip_prefix("Content-Length: "))
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nHello!").unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+
+        let body = json!({ "message": "héllo" }).to_string();
+        post("127.0.0.1", port, &body).unwrap();
+        let (head, received) = server.join().unwrap();
+
+        assert_eq!(head[0], "POST / HTTP/1.1");
+        assert!(head.contains(&format!("Host: 127.0.0.1:{port}")));
+        assert!(head.contains(&"Content-Type: application/json".to_string()));
+        assert!(head.contains(&"Connection: close".to_string()));
+        // The length counts bytes, not characters
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert_eq!(received, body);
+    }
+
+    #[test]
+    fn post_fails_when_nothing_listens() {
+        let port = TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port();
+        assert!(post("127.0.0.1", port, "{}").is_err());
+    }
+}
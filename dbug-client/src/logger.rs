@@ -0,0 +1,75 @@
+use crate::{flush, queue};
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+
+// How long `log::logger().flush()` waits for queued records
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A `log` backend that sends records to Dbug Desktop
+///
+/// Records are shown with the log view, tagged with their level, target and module path, and are
+/// sent on the same background thread as `dbug!`.
+///
+/// ```no_run
+/// dbug_client::DbugLogger::new().with_level(log::LevelFilter::Debug).init().unwrap();
+///
+/// log::warn!("cache miss for user {}", 42);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DbugLogger {
+    level: LevelFilter,
+}
+
+impl Default for DbugLogger {
+    fn default() -> Self {
+        Self { level: LevelFilter::Trace }
+    }
+}
+
+impl DbugLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends only records at `level` or more severe; everything is sent by default
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Installs the logger as the `log` crate's global logger
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        log::set_max_level(self.level);
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl Log for DbugLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut options = Map::new();
+        options.insert("type".to_string(), "log".into());
+        options.insert("level".to_string(), record.level().as_str().to_lowercase().into());
+        options.insert("target".to_string(), record.target().into());
+        if let Some(module) = record.module_path() {
+            options.insert("module".to_string(), module.into());
+        }
+        if let (Some(file), Some(line)) = (record.file(), record.line()) {
+            options.insert("file".to_string(), file.into());
+            options.insert("line".to_string(), line.into());
+        }
+        queue(&json!({ "$dbug": options, "data": Value::String(record.args().to_string()) }));
+    }
+
+    fn flush(&self) {
+        flush(FLUSH_TIMEOUT);
+    }
+}
@@ -1,74 +1,101 @@
-use crate::server::ServerMessage;
-use crate::settings::Settings;
-use crate::storage::Storage;
+use dbug::server::ServerMessage;
+use dbug::settings::{HotkeyAction, NewPayloadBehavior, Settings};
+use dbug::storage::{Payload, Storage};
 use iced::window;
 use iced::Task;
 use iced::event::Event;
-use serde_json::Value;
 use std::collections::HashSet;
-use std::collections::HashMap;
 
-use global_hotkey::{
-    hotkey::{Code, HotKey, Modifiers},
-    GlobalHotKeyManager,
-};
+use crate::hotkeys::HotkeyRegistry;
+use crate::components::typed::{LevelFilter, SpanTree};
+use crate::components::PayloadRows;
+use crate::instance::Forwarded;
 
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) enum HotkeyAction {
-    ShowWindow,
-    ClearPayloads,
-}
-
 pub(crate) struct App {
     pub(crate) show_modal: bool,
     pub(crate) settings: Settings,
     pub(crate) storage: Storage,
     pub(crate) expanded_payload_id: Option<String>,
+    /// Payload highlighted by keyboard navigation
+    pub(crate) selected_payload_id: Option<String>,
     pub(crate) collapsed_json_lines: HashSet<usize>,
-    pub(crate) payload_list_cache: Vec<(String, Value)>,
+    pub(crate) payload_list_cache: Vec<Payload>,
+    /// Payloads shown inside their span rather than as rows of `payload_list_cache`
+    pub(crate) span_tree: SpanTree,
     pub(crate) search_query: String,
-    _hotkey_manager: GlobalHotKeyManager,
+    /// Least severe log level shown in the list
+    pub(crate) level_filter: LevelFilter,
+    pub(crate) rejected_requests: usize,
+    pub(crate) last_rejection: Option<String>,
+    /// Set by the user to hold new payloads out of the list
+    pub(crate) paused: bool,
+    /// Set while the user inspects a payload they expanded, holding new payloads like a pause
+    pub(crate) focus_locked: bool,
+    /// Stored payloads held out of the list until the user asks to see them
+    pub(crate) held_payload_ids: HashSet<String>,
     pub(crate) main_window_id: Option<window::Id>,
-    pub(crate) hotkey_actions: HashMap<u32, HotkeyAction>,
+    pub(crate) hotkeys: HotkeyRegistry,
+    /// Action whose hotkey is being recorded from the next key press
+    pub(crate) capturing_hotkey: Option<HotkeyAction>,
+    /// Why the last hotkey change was refused
+    pub(crate) hotkey_error: Option<String>,
 }
 
 impl App {
     pub(crate) fn default() -> (Self, Task<Message>) {
-        let storage = Storage::new().expect("Failed to initialize storage");
-        let payload_list_cache = storage.get_all();
-        let newest_payload_id = payload_list_cache.first().map(|(id, _)| id.clone());
-
-        let manager = GlobalHotKeyManager::new().expect("Failed to create GlobalHotKeyManager");
-        let mut hotkey_actions = HashMap::new();
-
-        let hotkey_visible = HotKey::new(Some(Modifiers::SHIFT | Modifiers::SUPER), Code::KeyL);
-        let hotkey_clear_payloads = HotKey::new(Some(Modifiers::SHIFT | Modifiers::SUPER), Code::KeyK);
+        let settings = Settings::load();
+        let mut storage = Storage::new().expect("Failed to initialize storage");
+        storage.set_move_updated_to_top(settings.is_move_updated_to_top());
+        let (payload_list_cache, span_tree) = SpanTree::split(storage.get_all());
+        let newest_payload_id = payload_list_cache.first().map(|payload| payload.id.clone());
 
-        hotkey_actions.insert(hotkey_visible.id, HotkeyAction::ShowWindow);
-        hotkey_actions.insert(hotkey_clear_payloads.id, HotkeyAction::ClearPayloads);
-
-        manager.register(hotkey_visible).expect("Failed to register ShowWindow hotkey");
-        manager.register(hotkey_clear_payloads).expect("Failed to register ClearPayloads hotkey");
+        // Failed bindings are recorded by the registry and shown in Settings
+        let mut hotkeys = HotkeyRegistry::new();
+        if hotkeys.unavailable_reason().is_none() {
+            for (action, shortcut) in settings.get_hotkeys() {
+                if let Err(e) = hotkeys.register(*action, shortcut) {
+                    eprintln!("WARN: {e}");
+                }
+            }
+        }
 
         let app = Self {
             show_modal: false,
-            settings: Settings::load(),
+            settings,
             storage,
             expanded_payload_id: newest_payload_id,
+            selected_payload_id: None,
             collapsed_json_lines: HashSet::new(),
             payload_list_cache,
+            span_tree,
             search_query: String::new(),
-            _hotkey_manager: manager,
+            level_filter: LevelFilter::default(),
+            rejected_requests: 0,
+            last_rejection: None,
+            paused: false,
+            focus_locked: false,
+            held_payload_ids: HashSet::new(),
             main_window_id: None,
-            hotkey_actions,
+            hotkeys,
+            capturing_hotkey: None,
+            hotkey_error: None,
         };
 
         (app, Task::none())
     }
 }
 
+/// How to scroll the payload list once its rows are measured
+#[derive(Debug, Clone)]
+pub(crate) enum RowScroll {
+    /// Scroll just enough to bring the payload's row into view
+    Reveal(String),
+    /// Scroll down by the space these rows, added above the expanded payload, take up
+    Shift(Vec<String>),
+}
+
 /// Messages used for application state updates
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -81,17 +108,33 @@ pub(crate) enum Message {
     TogglePayload(String),
     ToggleJsonSection(usize),
     ClearPayloads,
+    TogglePause,
+    ShowHeldPayloads,
     DeletePayload(String),
+    TogglePin(String),
+    PayloadRowsMeasured(PayloadRows, RowScroll),
     WindowMoved(iced::Point),
     WindowResized(iced::Size),
     WindowClosed,
     SearchQueryChanged(String),
+    LevelFilterChanged(LevelFilter),
     // Re-introduce HotkeyActivated
     HotkeyActivated(u32),
+    Forwarded(Forwarded),
+    CheckStorage,
+    CaptureHotkey(HotkeyAction),
+    ClearHotkey(HotkeyAction),
     CaptureWindowId(window::Id),
     OpenSettings,
     ServerHostChanged(String),
     ServerPortChanged(String),
+    TcpPortChanged(String),
+    UdpPortChanged(String),
+    UnixSocketToggled(bool),
+    UnixSocketPathChanged(String),
+    MaxBodySizeChanged(String),
+    MoveUpdatedToTopToggled(bool),
+    NewPayloadBehaviorChanged(NewPayloadBehavior),
     ResetServerToDefaults,
     CopyJsonToClipboard(String),
 }
\ No newline at end of file
@@ -0,0 +1,211 @@
+use crate::headless::OutputFormat;
+use dbug::protocol;
+use dbug::settings::Settings;
+use dbug::storage::{Payload, Storage};
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Subcommands handled here rather than by the GUI
+pub const COMMANDS: [&str; 7] = ["send", "tail", "list", "show", "export", "clear", "help"];
+
+const USAGE: &str = "\
+Usage: dbug [COMMAND]
+       dbug [OPTIONS] [FILE...]
+
+Without a command, opens the window and imports each FILE, e.g. one written by `export`.
+If dbug is already running, the files are handed to it and its window is brought forward.
+Commands:
+  send <FILE|->        Send JSON from a file or stdin to the running instance
+  tail [--format F]    Print payloads as the running instance receives them
+  list                 List stored payloads, newest first
+  show <ID>            Print a stored payload
+  export [FILE]        Write all stored payloads as a JSON array
+  clear                Delete all stored payloads
+  help                 Show this message
+
+Options:
+  --headless           Serve and store payloads without a window
+  --tui                Show payloads in the terminal instead of a window
+  --format F           Output for --headless and tail: json, pretty or summary";
+
+// How long to wait when checking for a running instance
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+
+// Longest value shown per line by `list`
+const LIST_PREVIEW_CHARS: usize = 100;
+
+/// Runs a subcommand from `COMMANDS` with the arguments after it
+pub fn run(command: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+    // Output piped into e.g. `head` may be cut off; that is not an error
+    run_command(command, args).or_else(|e| match e.downcast_ref::<io::Error>() {
+        Some(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        _ => Err(e),
+    })
+}
+
+fn run_command(command: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let settings = Settings::load();
+    match command {
+        "send" => send(&settings, args.first().map_or("-", String::as_str)),
+        "tail" => {
+            let format = match crate::option_value(args, "--format") {
+                Some(name) => OutputFormat::parse(&name).ok_or_else(|| format!("Unknown format {name}"))?,
+                None => OutputFormat::Summary,
+            };
+            tail(&settings, format)
+        }
+        "list" => list(),
+        "show" => show(args.first().ok_or("Usage: dbug show <ID>")?),
+        "export" => export(args.first().map(String::as_str)),
+        "clear" => clear(&settings),
+        _ => {
+            writeln!(io::stdout(), "{USAGE}")?;
+            Ok(())
+        }
+    }
+}
+
+fn server_url(settings: &Settings) -> String {
+    format!("http://{}:{}", settings.get_server_host(), settings.get_server_port())
+}
+
+/// The running instance's address, if something is listening on the configured port
+fn running_instance(settings: &Settings) -> Option<SocketAddr> {
+    let addr = (settings.get_server_host(), settings.get_server_port())
+        .to_socket_addrs()
+        .ok()?
+        .next()?;
+    TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok().map(|_| addr)
+}
+
+fn runtime() -> io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread().enable_all().build()
+}
+
+/// Posts JSON to the running instance, or stores it directly when none is running
+fn send(settings: &Settings, source: &str) -> Result<(), Box<dyn Error>> {
+    let mut body = String::new();
+    if source == "-" {
+        io::stdin().read_to_string(&mut body)?;
+    } else {
+        body = std::fs::read_to_string(source)?;
+    }
+    let value: serde_json::Value = serde_json::from_str(&body).map_err(|e| format!("Invalid JSON in {source}: {e}"))?;
+
+    if running_instance(settings).is_none() {
+        Storage::new()?.add_payload(protocol::payload(value))?;
+        eprintln!("No running instance; stored the payload directly");
+        return Ok(());
+    }
+
+    runtime()?.block_on(async {
+        let response = reqwest::Client::new()
+            .post(server_url(settings))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        eprintln!("{}", response.text().await?);
+        Ok(())
+    })
+}
+
+/// Streams payloads from the running instance's `/tail` route
+fn tail(settings: &Settings, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    if running_instance(settings).is_none() {
+        return Err(format!(
+            "No dbug instance is running at {}; start the app or `dbug --headless` first",
+            server_url(settings)
+        )
+        .into());
+    }
+
+    runtime()?.block_on(async {
+        let mut response = reqwest::Client::new()
+            .get(format!("{}/tail", server_url(settings)))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // Chunks may split lines; keep the unfinished end for the next chunk
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                match serde_json::from_slice::<Payload>(&line) {
+                    Ok(payload) => {
+                        let mut stdout = io::stdout().lock();
+                        writeln!(stdout, "{}", format.format(&payload))?;
+                        stdout.flush()?;
+                    }
+                    Err(e) => eprintln!("WARN: Skipping unreadable payload: {e}"),
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Prints one line per stored payload, newest first
+fn list() -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+    for payload in Storage::new()?.get_all() {
+        let mut preview = OutputFormat::Summary.format(&payload);
+        if let Some((idx, _)) = preview.char_indices().nth(LIST_PREVIEW_CHARS) {
+            preview.truncate(idx);
+            preview.push('…');
+        }
+        writeln!(stdout, "{}  {preview}", payload.id)?;
+    }
+    Ok(())
+}
+
+fn show(id: &str) -> Result<(), Box<dyn Error>> {
+    let payload = Storage::new()?
+        .get_all()
+        .into_iter()
+        .find(|payload| payload.id == id)
+        .ok_or_else(|| format!("No payload with ID {id}"))?;
+    let mut stdout = io::stdout().lock();
+    for (key, value) in &payload.meta {
+        writeln!(stdout, "{key}: {value}")?;
+    }
+    writeln!(stdout, "{}", serde_json::to_string_pretty(&payload.document())?)?;
+    Ok(())
+}
+
+/// Writes every stored payload, oldest first, to a file or stdout
+fn export(path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut payloads = Storage::new()?.get_all();
+    payloads.reverse();
+    let json = serde_json::to_string_pretty(&payloads)?;
+    match path {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            eprintln!("Exported {} payloads to {path}", payloads.len());
+        }
+        None => writeln!(io::stdout(), "{json}")?,
+    }
+    Ok(())
+}
+
+/// Clears through the running instance so its list stays in sync, or directly when none is running
+fn clear(settings: &Settings) -> Result<(), Box<dyn Error>> {
+    if running_instance(settings).is_none() {
+        Storage::new()?.delete_all()?;
+        return Ok(());
+    }
+
+    runtime()?.block_on(async {
+        reqwest::Client::new()
+            .delete(format!("{}/payloads", server_url(settings)))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    })
+}
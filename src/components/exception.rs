@@ -0,0 +1,125 @@
+use crate::app::Message;
+use iced::widget::{column, row, text};
+use iced::{Element, Theme};
+use serde_json::Value;
+
+// Frames beyond this are left to the raw JSON below the summary
+const MAX_FRAMES: usize = 30;
+
+/// Returns the exceptions of an event, in Sentry's order: the primary exception last
+///
+/// A typed dbug exception, which carries `type` and `frames` at the top level rather than
+/// a Sentry `event_id`, is its own single exception.
+fn exception_values(event: &Value) -> &[Value] {
+    let Some(exception) = event.get("exception") else {
+        return if event.get("type").is_some() && event.get("event_id").is_none() {
+            std::slice::from_ref(event)
+        } else {
+            &[]
+        };
+    };
+    exception
+        .get("values")
+        .unwrap_or(exception)
+        .as_array()
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Returns the frames of an exception, most recent call first
+fn exception_frames(exception: &Value) -> Vec<&Value> {
+    // Sentry lists stack frames oldest first; typed dbug exceptions list them most recent first
+    match exception.get("stacktrace").and_then(|stacktrace| stacktrace.get("frames")).and_then(Value::as_array) {
+        Some(frames) => frames.iter().rev().collect(),
+        None => exception
+            .get("frames")
+            .and_then(Value::as_array)
+            .map(|frames| frames.iter().collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the event message, which may be a plain string or a formatted log entry
+fn event_message(event: &Value) -> Option<&str> {
+    ["message", "logentry"].iter().find_map(|key| {
+        let message = event.get(key)?;
+        message
+            .as_str()
+            .or_else(|| message.get("formatted")?.as_str())
+            .or_else(|| message.get("message")?.as_str())
+    })
+}
+
+/// Formats a frame's location as "file:line"
+fn frame_location(frame: &Value) -> String {
+    let file = ["filename", "file", "abs_path", "module"]
+        .iter()
+        .find_map(|key| frame.get(key)?.as_str())
+        .unwrap_or("?");
+    match frame.get("lineno").or_else(|| frame.get("line")).and_then(Value::as_u64) {
+        Some(line) => format!("{file}:{line}"),
+        None => file.to_string(),
+    }
+}
+
+/// Renders the exception type, message and stack frames of an exception payload
+pub fn exception_summary<'a>(event: &Value, theme: &Theme) -> Element<'a, Message> {
+    let palette = theme.extended_palette();
+    let error_color = palette.danger.base.color;
+    let function_color = palette.primary.strong.color;
+    let muted_color = palette.background.strong.color;
+
+    let mut sections = Vec::new();
+
+    // Show the primary exception first, then the exceptions that caused it
+    for exception in exception_values(event).iter().rev() {
+        let kind = exception.get("type").and_then(Value::as_str).unwrap_or("Exception");
+        let message = exception.get("value").or_else(|| exception.get("message"));
+        let headline = match message.and_then(Value::as_str) {
+            Some(value) => format!("{kind}: {value}"),
+            None => kind.to_string(),
+        };
+
+        let frame_rows = exception_frames(exception).into_iter().take(MAX_FRAMES).map(|frame| {
+            let function = frame.get("function").and_then(Value::as_str).unwrap_or("<unknown>");
+            let in_app = frame.get("in_app").and_then(Value::as_bool).unwrap_or(true);
+            let function_color = if in_app { function_color } else { muted_color };
+            row![
+                text(function.to_string())
+                    .size(12)
+                    .style(move |_| text::Style { color: Some(function_color) }),
+                text(frame_location(frame))
+                    .size(11)
+                    .style(move |_| text::Style { color: Some(muted_color) }),
+            ]
+            .spacing(10)
+            .into()
+        });
+
+        sections.push(
+            column![
+                text(headline)
+                    .size(14)
+                    .style(move |_| text::Style { color: Some(error_color) }),
+                column(frame_rows).spacing(2).padding(iced_core::Padding {
+                    left: 10.0,
+                    ..Default::default()
+                }),
+            ]
+            .spacing(5)
+            .into(),
+        );
+    }
+
+    if sections.is_empty() {
+        if let Some(message) = event_message(event) {
+            sections.push(
+                text(message.to_string())
+                    .size(14)
+                    .style(move |_| text::Style { color: Some(error_color) })
+                    .into(),
+            );
+        }
+    }
+
+    column(sections).spacing(10).into()
+}
@@ -1,9 +1,11 @@
+pub mod exception;
 pub(crate) mod json_highlight;
 pub mod modal;
 pub mod payloads;
 pub mod settings;
 pub(crate) mod styles;
+pub mod typed;
 
 pub use modal::modal;
-pub use payloads::payload_list;
+pub use payloads::{measure_rows, payload_json, payload_list, PayloadRows, PAYLOAD_SCROLL_ID, SEARCH_INPUT_ID};
 pub use settings::settings_modal;
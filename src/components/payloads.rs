@@ -1,13 +1,17 @@
 use crate::app::Message;
 use crate::components::json_highlight::highlight_json;
+use crate::components::typed::{self, Aggregates, SpanTree};
 use crate::components::styles;
+use dbug::storage::Payload;
 use chrono::{DateTime, Utc};
 use core::time::Duration;
-use iced::widget::{button, column, container, row, scrollable, stack, svg, text, text_input};
-use iced::{Element, Fill, Theme, Center};
+use iced::widget::{button, column, container, horizontal_space, row, scrollable, stack, svg, text, text_input, tooltip};
+use iced::advanced::widget::{self, operate, operation, Operation};
+use iced::advanced::widget::operation::Outcome;
+use iced::{Element, Fill, Theme, Center, Rectangle, Task, Vector};
 use millisecond::prelude::*;
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Converts a timestamp ID into a human-readable relative time string
 fn human_readable_time(id: &str) -> String {
@@ -21,31 +25,236 @@ fn human_readable_time(id: &str) -> String {
         )
 }
 
+/// Summarizes the upstream response status of a relayed payload, e.g. "→ 200"
+fn response_status(payload: &Payload) -> String {
+    payload
+        .response
+        .as_ref()
+        .and_then(|response| response.get("status"))
+        .map_or_else(String::new, |status| format!("→ {status}"))
+}
+
+// Longest value preview shown for a revision
+const REVISION_PREVIEW_CHARS: usize = 120;
+
+/// Counts the updates a payload has received in place, e.g. "rev 3"
+fn revision_label(payload: &Payload) -> String {
+    match payload.revisions.len() {
+        0 => String::new(),
+        count => format!("rev {}", count + 1),
+    }
+}
+
+/// Shows how often a repeated payload arrived, e.g. "×42", with first and last seen times on hover
+fn repeat_badge<'a>(payload: &Payload) -> Element<'a, Message> {
+    let Some(last_seen) = payload.last_seen.as_deref() else {
+        return horizontal_space().width(0).into();
+    };
+    let badge = container(text(format!("×{}", payload.count())).size(10))
+        .padding([1, 4])
+        .style(styles::container_badge);
+    let seen = text(format!(
+        "first seen {} · last seen {}",
+        human_readable_time(&payload.id),
+        human_readable_time(last_seen)
+    ))
+    .size(10);
+    tooltip(badge, container(seen).padding(5).style(container::rounded_box), tooltip::Position::Bottom).into()
+}
+
+/// Lists the previous values of a payload updated in place, newest first
+fn revision_history<'a>(payload: &Payload) -> Option<Element<'a, Message>> {
+    if payload.revisions.is_empty() {
+        return None;
+    }
+    let rows = payload.revisions.iter().rev().map(|revision| {
+        let mut preview = revision.value.to_string();
+        if let Some((idx, _)) = preview.char_indices().nth(REVISION_PREVIEW_CHARS) {
+            preview.truncate(idx);
+            preview.push('…');
+        }
+        row![
+            text(human_readable_time(&revision.received)).size(10).width(90),
+            text(preview).size(12),
+        ]
+        .spacing(10)
+        .into()
+    });
+    Some(
+        column![
+            text(format!("Revisions ({})", payload.revisions.len())).size(12),
+            column(rows).spacing(4),
+        ]
+        .spacing(6)
+        .into(),
+    )
+}
+
+/// Renders a payload's metadata as small "key: value" badges
+fn meta_badges<'a>(payload: &Payload) -> Element<'a, Message> {
+    row(payload.meta.iter().map(|(key, value)| {
+        let value = match value {
+            Value::String(string) => string.clone(),
+            other => other.to_string(),
+        };
+        container(text(format!("{key}: {value}")).size(10))
+            .padding([1, 4])
+            .style(styles::container_badge)
+            .into()
+    }))
+    .spacing(4)
+    .into()
+}
+
+/// ID of the search field in the expanded payload's header
+pub const SEARCH_INPUT_ID: &str = "payload_search";
+
+/// ID of the scrollable holding the payload rows
+pub const PAYLOAD_SCROLL_ID: &str = "payload_scroll";
+
+/// ID of the container around a payload's row, so the row can be found once laid out
+fn row_id(payload_id: &str) -> container::Id {
+    container::Id::new(format!("payload_row_{payload_id}"))
+}
+
+/// Where the rows of the payload list are laid out, as last measured on screen
+#[derive(Debug, Clone, Default)]
+pub struct PayloadRows {
+    /// Payload ID with the top and bottom of its row within the list's content, in list order
+    rows: Vec<(String, f32, f32)>,
+    /// How far the list is scrolled down
+    pub offset: f32,
+    /// Visible height of the list
+    pub height: f32,
+}
+
+impl PayloadRows {
+    /// Top and bottom of the payload's row within the list's content
+    pub fn bounds(&self, id: &str) -> Option<(f32, f32)> {
+        self.rows
+            .iter()
+            .find(|(row_id, ..)| row_id == id)
+            .map(|(_, top, bottom)| (*top, *bottom))
+    }
+
+    /// Space the payload's row takes up, from its top to the top of the next row
+    pub fn pitch(&self, id: &str) -> Option<f32> {
+        let idx = self.rows.iter().position(|(row_id, ..)| row_id == id)?;
+        let (_, top, bottom) = &self.rows[idx];
+        let next_top = self.rows.get(idx + 1).map_or(*bottom, |(_, next_top, _)| *next_top);
+        Some(next_top - top)
+    }
+}
+
+/// Measures the rows of the given payloads in the payload list as it is laid out
+pub fn measure_rows(payload_ids: impl IntoIterator<Item = String>) -> Task<PayloadRows> {
+    struct MeasureRows {
+        list: widget::Id,
+        rows: HashMap<widget::Id, String>,
+        content_top: f32,
+        measured: PayloadRows,
+    }
+
+    impl Operation<PayloadRows> for MeasureRows {
+        fn scrollable(
+            &mut self,
+            _state: &mut dyn operation::Scrollable,
+            id: Option<&widget::Id>,
+            bounds: Rectangle,
+            content_bounds: Rectangle,
+            translation: Vector,
+        ) {
+            if id == Some(&self.list) {
+                self.content_top = content_bounds.y;
+                self.measured.offset = translation.y;
+                self.measured.height = bounds.height;
+            }
+        }
+
+        fn container(
+            &mut self,
+            id: Option<&widget::Id>,
+            bounds: Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation<PayloadRows>),
+        ) {
+            match id.and_then(|id| self.rows.get(id)).cloned() {
+                // Nothing inside a row needs measuring
+                Some(payload_id) => self.measured.rows.push((payload_id, bounds.y, bounds.y + bounds.height)),
+                None => operate_on_children(self),
+            }
+        }
+
+        fn finish(&self) -> Outcome<PayloadRows> {
+            let mut measured = self.measured.clone();
+            for (_, top, bottom) in &mut measured.rows {
+                *top -= self.content_top;
+                *bottom -= self.content_top;
+            }
+            Outcome::Some(measured)
+        }
+    }
+
+    operate(MeasureRows {
+        list: scrollable::Id::new(PAYLOAD_SCROLL_ID).into(),
+        rows: payload_ids
+            .into_iter()
+            .map(|payload_id| (row_id(&payload_id).into(), payload_id))
+            .collect(),
+        content_top: 0.0,
+        measured: PayloadRows::default(),
+    })
+}
+
+/// Pretty-printed JSON for a payload; relayed payloads show the request and upstream response together
+pub fn payload_json(payload: &Payload) -> String {
+    serde_json::to_string_pretty(&payload.document()).unwrap_or_else(|err| {
+        eprintln!("Error prettifying payload {}: {err}", payload.id);
+        format!("{{ \"error\": \"Failed to render JSON: {err}\" }}")
+    })
+}
+
 /// Creates a scrollable display of received JSON payloads using cached data
 pub fn payload_list<'a>(
-    payloads: &'a [(String, Value)],
+    payloads: &'a [Payload],
+    spans: &SpanTree,
     expanded_id: Option<&String>,
+    selected_id: Option<&String>,
     theme: &Theme,
     collapsed_json_lines: &HashSet<usize>,
     max_payload_height: f32,
     search_query: &str,
 ) -> Element<'a, Message> {
+    let aggregates = Aggregates::from_payloads(payloads.iter().chain(spans.nested()));
+
     let storage_rows = column(
         payloads
             .iter()
-            .map(|(id, value)| {
+            .map(|payload| {
+                let id = &payload.id;
+                let value = &payload.value;
                 let is_expanded = expanded_id == Some(id);
-                let timestamp = human_readable_time(id);
+                let is_selected = selected_id == Some(id);
+                let timestamp = human_readable_time(payload.received());
 
-                if is_expanded {
-                    let pretty_json = serde_json::to_string_pretty(value).unwrap_or_else(|err| {
-                        eprintln!("Error prettifying payload {id}: {err}");
-                        format!("{{ \"error\": \"Failed to render JSON: {err}\" }}")
-                    });
+                let row: Element<'a, Message> = if is_expanded {
+                    let pretty_json = payload_json(payload);
 
                     let highlighted_json =
                         highlight_json(&pretty_json, theme, collapsed_json_lines, search_query);
 
+                    // Typed payloads use their own view; exceptions and spans keep the raw JSON below theirs
+                    let body: Element<'a, Message> = match typed::view(payload, theme, &aggregates, spans) {
+                        Some(view) if matches!(typed::kind(payload), Some("exception" | "span")) => {
+                            column![view, highlighted_json].spacing(10).into()
+                        }
+                        Some(view) => view,
+                        None => highlighted_json,
+                    };
+                    let body: Element<'a, Message> = match revision_history(payload) {
+                        Some(history) => column![body, history].spacing(15).into(),
+                        None => body,
+                    };
+
                     let close_svg = svg(svg::Handle::from_memory(
                         include_bytes!("../../assets/icons/mdi--caret-down.svg").as_slice(),
                     ))
@@ -60,6 +269,13 @@ pub fn payload_list<'a>(
                         .height(Fill)
                         .style(styles::svg_style_primary);
 
+                    let pin_svg = svg(svg::Handle::from_memory(
+                        include_bytes!("../../assets/icons/mdi--pin.svg").as_slice(),
+                    ))
+                        .width(Fill)
+                        .height(Fill)
+                        .style(styles::svg_style_secondary);
+
                     let copy_svg = svg(svg::Handle::from_memory(
                         include_bytes!("../../assets/icons/mdi--content-copy.svg").as_slice(),
                     ))
@@ -70,7 +286,7 @@ pub fn payload_list<'a>(
                     container(
                         stack![
                             container(
-                                scrollable(container(highlighted_json).padding(10))
+                                scrollable(container(body).padding(10))
                                     .direction(scrollable::Direction::Both {
                                         vertical: scrollable::Scrollbar::new()
                                             .width(3)
@@ -90,15 +306,24 @@ pub fn payload_list<'a>(
                             .max_height(max_payload_height),
                             container(
                                 row![
+                                    meta_badges(payload),
+                                    repeat_badge(payload),
                                     container(text(timestamp).size(10.0))
                                         .padding(3.0)
                                         .align_x(iced::alignment::Horizontal::Right)
                                         .align_y(iced::alignment::Vertical::Bottom)
                                         .width(Fill),
                                     text_input("Search...", search_query).width(150.0)
+                                        .id(text_input::Id::new(SEARCH_INPUT_ID))
                                         .on_input(Message::SearchQueryChanged)
                                         .size(13)
                                         .padding(2),
+                                    button(pin_svg)
+                                        .style(if payload.pinned { button::primary } else { button::secondary })
+                                        .width(18)
+                                        .height(18)
+                                        .padding(2)
+                                        .on_press(Message::TogglePin(id.clone())),
                                     button(copy_svg)
                                         .style(button::secondary)
                                         .width(18)
@@ -145,11 +370,40 @@ pub fn payload_list<'a>(
                         .height(Fill)
                         .style(styles::svg_style_primary);
 
+                    let summary_color = typed::summary_color(payload, theme);
+
+                    // Pinned payloads are marked in their collapsed row
+                    let pin_marker: Element<'a, Message> = if payload.pinned {
+                        svg(svg::Handle::from_memory(
+                            include_bytes!("../../assets/icons/mdi--pin.svg").as_slice(),
+                        ))
+                            .width(14)
+                            .height(14)
+                            .style(styles::svg_style_primary)
+                            .into()
+                    } else {
+                        horizontal_space().width(0).into()
+                    };
+
                     button(
                         container(
                             row![
-                                container(text(format!("{value}")).size(14).height(18.0))
+                                pin_marker,
+                                container(
+                                    text(typed::summary(payload, &aggregates, spans).unwrap_or_else(|| value.to_string()))
+                                        .size(14)
+                                        .height(18.0)
+                                        .style(move |_| text::Style { color: summary_color }),
+                                )
                                     .width(Fill),
+                                meta_badges(payload),
+                                repeat_badge(payload),
+                                container(text(revision_label(payload)).size(10.0))
+                                    .padding(4.0)
+                                    .align_y(iced::alignment::Vertical::Center),
+                                container(text(response_status(payload)).size(10.0))
+                                    .padding(4.0)
+                                    .align_y(iced::alignment::Vertical::Center),
                                 container(text(timestamp).size(10.0))
                                     .padding(4.0)
                                     .align_x(iced::alignment::Horizontal::Right)
@@ -166,14 +420,15 @@ pub fn payload_list<'a>(
                         )
                             .padding(10)
                             .width(Fill)
-                            .style(styles::container_code_closed),
+                            .style(if is_selected { styles::container_code_selected } else { styles::container_code_closed }),
                     )
                         .style(button::text)
                         .width(Fill)
                         .on_press(Message::TogglePayload(id.clone()))
                         .padding(0)
                         .into()
-                }
+                };
+                container(row).id(row_id(id)).into()
             })
             .collect::<Vec<_>>(),
     )
@@ -192,7 +447,7 @@ pub fn payload_list<'a>(
         .direction(scrollable::Direction::Vertical(
             scrollable::Scrollbar::new().width(5).scroller_width(5),
         ))
-        .id(iced::widget::scrollable::Id::new("payload_scroll"))
+        .id(scrollable::Id::new(PAYLOAD_SCROLL_ID))
         .width(Fill)
         .height(Fill)
         .into()
@@ -1,11 +1,19 @@
 use crate::app::Message;
-use crate::settings::Settings;
-use iced::widget::{column, container, radio, scrollable, text, row, horizontal_space, Row, text_input, button};
+use dbug::settings::{HotkeyAction, NewPayloadBehavior, Settings};
+use iced::widget::{checkbox, column, container, radio, scrollable, text, row, horizontal_space, Row, text_input, button};
 use iced::{Element, Fill, Theme, Length};
 use crate::components::styles;
+use crate::hotkeys::{self, HotkeyRegistry};
+use crate::keymap;
 
 /// Creates the settings modal content with theme selection
-pub fn settings_modal<'a>(current_theme: Theme, settings: &Settings) -> Element<'a, Message> {
+pub fn settings_modal<'a>(
+    current_theme: Theme,
+    settings: &Settings,
+    hotkey_registry: &HotkeyRegistry,
+    capturing_hotkey: Option<HotkeyAction>,
+    hotkey_error: Option<String>,
+) -> Element<'a, Message> {
     // Find the current theme index in Theme::ALL
     let current_index = Theme::ALL
         .iter()
@@ -22,9 +30,9 @@ pub fn settings_modal<'a>(current_theme: Theme, settings: &Settings) -> Element<
     };
 
     // Shortcut rows
-    let shortcut_row = |shortcut: &'a str, description: &'a str| -> Row<'a, Message> {
+    let shortcut_row = |shortcut: String, description: &'a str| -> Row<'a, Message> {
         row![
-            text(shortcut).width(Length::Fixed(60.0)).size(12),
+            text(shortcut).width(Length::Fixed(90.0)).size(12),
             horizontal_space().width(30.0),
             text(description).size(12),
         ]
@@ -32,15 +40,84 @@ pub fn settings_modal<'a>(current_theme: Theme, settings: &Settings) -> Element<
         .padding(5)
     };
 
+    // Global hotkey rows: click the shortcut to record a new one
+    let hotkey_row = |action: HotkeyAction| -> Row<'a, Message> {
+        let label = if capturing_hotkey == Some(action) {
+            "Press keys…".to_string()
+        } else {
+            settings.get_hotkey(action).map_or_else(|| "Not set".to_string(), hotkeys::display)
+        };
+        // Configured but not registered, e.g. taken by another application
+        let failure: Element<'a, Message> = match hotkey_registry.failure(action) {
+            Some(failure) => text(failure.to_string()).size(10).style(text::danger).into(),
+            None => column![].into(),
+        };
+        row![
+            button(text(label).size(12))
+                .width(Length::Fixed(120.0))
+                .padding([2, 5])
+                .style(button::secondary)
+                .on_press(Message::CaptureHotkey(action)),
+            column![text(action.description()).size(12), failure].width(Fill),
+            button(text("Clear").size(11))
+                .padding([2, 5])
+                .style(button::text)
+                .on_press_maybe(settings.get_hotkey(action).map(|_| Message::ClearHotkey(action))),
+        ]
+        .spacing(10)
+        .padding(5)
+        .align_y(iced::Center)
+    };
+    let hotkey_error_row: Element<'a, Message> = match hotkey_error {
+        Some(error) => text(error).size(12).style(text::danger).into(),
+        None => column![].into(),
+    };
+    let hotkey_rows: Element<'a, Message> = match hotkey_registry.unavailable_reason() {
+        Some(reason) => text(format!("Global hotkeys are unavailable on this system: {reason}"))
+            .size(12)
+            .style(text::danger)
+            .into(),
+        None => column![
+            column(HotkeyAction::ALL.into_iter().map(|action| hotkey_row(action).into())),
+            hotkey_error_row,
+        ]
+        .into(),
+    };
+
+    // The Unix socket listener is only available on Unix platforms
+    let unix_socket_rows: Element<'a, Message> = if cfg!(unix) {
+        column![
+            row![
+                text("Socket:").width(Length::Fixed(60.0)).size(12),
+                checkbox("Listen on a Unix socket", settings.is_unix_socket_enabled())
+                    .on_toggle(Message::UnixSocketToggled)
+                    .text_size(12)
+                    .size(14),
+            ].spacing(10).padding(5),
+            row![
+                horizontal_space().width(Length::Fixed(60.0)),
+                text_input("~/.dbug_desktop/dbug.sock", &settings.get_unix_socket_path().to_string_lossy())
+                    .on_input(Message::UnixSocketPathChanged)
+                    .width(Fill)
+                    .size(12),
+            ].spacing(10).padding(5),
+        ].into()
+    } else {
+        column![].into()
+    };
+
     container(
         scrollable(
             column![
                 // --- Shortcuts Section ---
                 container(text("Shortcuts").size(16).style(header_style)).style(header_background_style).width(Fill).padding(5),
                 column![
-                    shortcut_row("Shift+Cmd+L", "Toggle visibility from anywhere"),
-                    shortcut_row("Shift+Cmd+K", "Clear payloads from anywhere"),
-                    shortcut_row("Cmd+,", "Open Settings"),
+                    hotkey_rows,
+                    column(
+                        keymap::shortcuts()
+                            .into_iter()
+                            .map(|(shortcut, description)| shortcut_row(shortcut, description).into()),
+                    ).spacing(5),
                 ].spacing(5).padding(iced_core::Padding { top: 5.0, bottom: 15.0, right: 15.0, ..Default::default() }),
 
                 // --- Theme Selection Section ---
@@ -92,6 +169,33 @@ pub fn settings_modal<'a>(current_theme: Theme, settings: &Settings) -> Element<
                     bottom: 15.0,
                 }),
 
+                // --- Payloads Section ---
+                container(text("Payloads").size(16).style(header_style)).style(header_background_style).width(Fill).padding(5),
+                column![
+                    row![
+                        text("Updates:").width(Length::Fixed(60.0)).size(12),
+                        checkbox("Move updated payloads to the top", settings.is_move_updated_to_top())
+                            .on_toggle(Message::MoveUpdatedToTopToggled)
+                            .text_size(12)
+                            .size(14),
+                    ].spacing(10).padding(5),
+                    row![
+                        text("New:").width(Length::Fixed(60.0)).size(12),
+                        column(NewPayloadBehavior::ALL.iter().map(|&behavior| {
+                            radio(
+                                behavior.label(),
+                                behavior,
+                                Some(settings.get_new_payload_behavior()),
+                                Message::NewPayloadBehaviorChanged,
+                            )
+                            .text_size(12)
+                            .size(14)
+                            .into()
+                        }))
+                        .spacing(8),
+                    ].spacing(10).padding(5),
+                ].spacing(5).padding(iced_core::Padding { top: 5.0, bottom: 15.0, right: 15.0, ..Default::default() }),
+
                 // --- Server Configuration Section ---
                 container(text("Server Configuration").size(16).style(header_style)).style(header_background_style).width(Fill).padding(5),
                 column![
@@ -109,6 +213,28 @@ pub fn settings_modal<'a>(current_theme: Theme, settings: &Settings) -> Element<
                             .width(Length::Fixed(120.0))
                             .size(12),
                     ].spacing(10).padding(5),
+                    row![
+                        text("TCP Port:").width(Length::Fixed(60.0)).size(12),
+                        text_input("Disabled", &settings.get_tcp_port().map(|port| port.to_string()).unwrap_or_default())
+                            .on_input(Message::TcpPortChanged)
+                            .width(Length::Fixed(120.0))
+                            .size(12),
+                    ].spacing(10).padding(5),
+                    row![
+                        text("UDP Port:").width(Length::Fixed(60.0)).size(12),
+                        text_input("Disabled", &settings.get_udp_port().map(|port| port.to_string()).unwrap_or_default())
+                            .on_input(Message::UdpPortChanged)
+                            .width(Length::Fixed(120.0))
+                            .size(12),
+                    ].spacing(10).padding(5),
+                    unix_socket_rows,
+                    row![
+                        text("Max KiB:").width(Length::Fixed(60.0)).size(12),
+                        text_input("2048", &(settings.get_max_body_bytes() / 1024).to_string())
+                            .on_input(Message::MaxBodySizeChanged)
+                            .width(Length::Fixed(120.0))
+                            .size(12),
+                    ].spacing(10).padding(5),
                     row![
                         horizontal_space(),
                         button(text("Set to Default").size(12))
@@ -57,3 +57,31 @@ pub(crate) fn container_code_closed(theme: &Theme) -> container::Style {
         }
     }
 }
+
+pub(crate) fn container_code_selected(theme: &Theme) -> container::Style {
+    {
+        let palette = theme.extended_palette();
+        let mut bg_color = palette.secondary.strong.color;
+        bg_color.a = 0.2;
+
+        container::Style {
+            background: Some(bg_color.into()),
+            border: iced_core::border::rounded(5).color(palette.primary.strong.color).width(1.0),
+            ..container::Style::default()
+        }
+    }
+}
+
+pub(crate) fn container_badge(theme: &Theme) -> container::Style {
+    {
+        let palette = theme.extended_palette();
+        let mut bg_color = palette.secondary.strong.color;
+        bg_color.a = 0.3;
+
+        container::Style {
+            background: Some(bg_color.into()),
+            border: iced_core::border::rounded(3),
+            ..container::Style::default()
+        }
+    }
+}
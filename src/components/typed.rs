@@ -0,0 +1,469 @@
+use crate::app::Message;
+use crate::components::exception::exception_summary;
+use crate::components::styles;
+use dbug::storage::Payload;
+use iced::widget::{column, container, horizontal_space, row, text, Column};
+use iced::{Center, Color, Element, Length, Theme};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+// Width of a table cell and of a full timer bar
+const CELL_WIDTH: f32 = 160.0;
+const TIMER_BAR_WIDTH: f32 = 240.0;
+
+// Indent of each level of a span's children, and how deep they are drawn
+const SPAN_INDENT: f32 = 16.0;
+const MAX_SPAN_DEPTH: usize = 16;
+
+/// Running statistics for timers that share a name
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimerStats {
+    pub count: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Counter totals and timer statistics aggregated across all payloads, keyed by name
+#[derive(Debug, Default)]
+pub struct Aggregates {
+    counters: HashMap<String, f64>,
+    timers: HashMap<String, TimerStats>,
+}
+
+impl Aggregates {
+    pub fn from_payloads<'a>(payloads: impl IntoIterator<Item = &'a Payload>) -> Self {
+        let mut aggregates = Self::default();
+        for payload in payloads {
+            match kind(payload) {
+                Some("counter") => {
+                    *aggregates.counters.entry(name(payload)).or_default() += counter_increment(&payload.value);
+                }
+                Some("timer") => {
+                    if let Some(duration) = timer_duration(&payload.value) {
+                        let stats = aggregates.timers.entry(name(payload)).or_default();
+                        stats.count += 1;
+                        stats.total_ms += duration;
+                        stats.max_ms = stats.max_ms.max(duration);
+                    }
+                }
+                _ => {}
+            }
+        }
+        aggregates
+    }
+}
+
+/// Events and child spans shown inside the span they happened in, instead of as rows of their own
+#[derive(Debug, Default)]
+pub struct SpanTree {
+    /// Payloads shown inside a span, oldest first
+    nested: Vec<Payload>,
+    /// Indices into `nested` by the client ID of the span they belong to
+    children: HashMap<String, Vec<usize>>,
+}
+
+impl SpanTree {
+    /// Moves payloads that belong to a span among `payloads` into the tree, returning the rest as list rows
+    pub fn split(payloads: Vec<Payload>) -> (Vec<Payload>, Self) {
+        let spans: HashSet<String> = payloads
+            .iter()
+            .filter(|payload| kind(payload) == Some("span"))
+            .filter_map(|payload| payload.client_id.clone())
+            .collect();
+
+        let mut tree = Self::default();
+        let mut rows = Vec::new();
+        // Payloads arrive newest first; children are listed in the order they happened
+        for payload in payloads.into_iter().rev() {
+            match parent_span(&payload).filter(|span| spans.contains(*span)).map(str::to_string) {
+                Some(span) => {
+                    tree.children.entry(span).or_default().push(tree.nested.len());
+                    tree.nested.push(payload);
+                }
+                None => rows.push(payload),
+            }
+        }
+        rows.reverse();
+        (rows, tree)
+    }
+
+    /// Every payload shown inside a span rather than as a row
+    pub fn nested(&self) -> &[Payload] {
+        &self.nested
+    }
+
+    fn children<'a>(&'a self, span: &Payload) -> impl Iterator<Item = &'a Payload> {
+        span.client_id
+            .as_ref()
+            .and_then(|id| self.children.get(id))
+            .into_iter()
+            .flatten()
+            .map(|&idx| &self.nested[idx])
+    }
+}
+
+/// The span a payload happened in: a log's `span`, or a span's `parent`
+fn parent_span(payload: &Payload) -> Option<&str> {
+    match kind(payload) {
+        Some("span") => meta_str(payload, "parent"),
+        _ => meta_str(payload, "span"),
+    }
+}
+
+/// The payload kind recorded in its metadata, e.g. "log" or "table"
+pub fn kind(payload: &Payload) -> Option<&str> {
+    payload.meta.get("kind").and_then(Value::as_str)
+}
+
+fn meta_str<'a>(payload: &'a Payload, key: &str) -> Option<&'a str> {
+    payload.meta.get(key).and_then(Value::as_str)
+}
+
+/// Counters and timers are grouped by their `name` option
+fn name(payload: &Payload) -> String {
+    meta_str(payload, "name").unwrap_or("default").to_string()
+}
+
+/// A counter's data is its increment: a number, `{"increment": n}`, or nothing for 1
+fn counter_increment(value: &Value) -> f64 {
+    value
+        .as_f64()
+        .or_else(|| value.get("increment")?.as_f64())
+        .unwrap_or(1.0)
+}
+
+/// A timer's data is its duration in milliseconds: a number or `{"duration_ms": n}`
+fn timer_duration(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.get("duration_ms")?.as_f64())
+}
+
+fn log_level(payload: &Payload) -> String {
+    meta_str(payload, "level")
+        .or_else(|| meta_str(payload, "severity"))
+        .unwrap_or("info")
+        .to_uppercase()
+}
+
+/// A log line's message: plain text, or the `message`/`body` of an object
+fn log_message(value: &Value) -> String {
+    let message = value
+        .get("message")
+        .or_else(|| value.get("body"))
+        .unwrap_or(value);
+    match message {
+        Value::String(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A span's name; spans from `DbugLayer` carry it in metadata, OTLP spans in their value
+fn span_name(payload: &Payload) -> String {
+    meta_str(payload, "name")
+        .or_else(|| payload.value.get("name")?.as_str())
+        .unwrap_or("span")
+        .to_string()
+}
+
+/// A span's duration, e.g. "12.34 ms", or "running" until it closes
+fn span_duration(value: &Value) -> String {
+    value
+        .get("duration_ms")
+        .and_then(Value::as_f64)
+        .map_or_else(|| "running".to_string(), |duration| format!("{duration:.2} ms"))
+}
+
+/// An object's fields as "key=value" pairs, leaving out `skip`
+fn span_fields(value: &Value, skip: &[&str]) -> String {
+    value
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| !skip.contains(&key.as_str()))
+        .map(|(key, value)| format!("{key}={}", cell_text(value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// One-line description of a typed payload for its collapsed row
+pub fn summary(payload: &Payload, aggregates: &Aggregates, spans: &SpanTree) -> Option<String> {
+    match kind(payload)? {
+        "span" => {
+            let name = span_name(payload);
+            let children = spans.children(payload).count();
+            Some(format!("{name} · {} · {children} nested", span_duration(&payload.value)))
+        }
+        "log" => Some(format!("[{}] {}", log_level(payload), log_message(&payload.value))),
+        "table" => {
+            let rows = payload.value.as_array().map_or(0, Vec::len);
+            Some(format!("table: {rows} rows"))
+        }
+        "timer" => {
+            let duration = timer_duration(&payload.value)?;
+            Some(format!("{}: {duration:.2} ms", name(payload)))
+        }
+        "counter" => {
+            let name = name(payload);
+            let total = aggregates.counters.get(&name).copied().unwrap_or_default();
+            Some(format!("{name}: {:+} (total {total})", counter_increment(&payload.value)))
+        }
+        _ => None,
+    }
+}
+
+fn colored<'a>(content: String, size: f32, color: Color) -> Element<'a, Message> {
+    text(content)
+        .size(size)
+        .style(move |_| text::Style { color: Some(color) })
+        .into()
+}
+
+/// Severity of a log level from 0 (trace) to 4 (error and worse); unknown levels count as info
+fn level_rank(level: &str) -> u8 {
+    match level {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "WARN" | "WARNING" => 3,
+        "ERROR" | "FATAL" | "CRITICAL" => 4,
+        _ => 2,
+    }
+}
+
+fn level_color(level: &str, theme: &Theme) -> Color {
+    let palette = theme.extended_palette();
+    match level_rank(level) {
+        4 => palette.danger.strong.color,
+        3 => palette.danger.weak.color,
+        0 | 1 => palette.background.strong.color,
+        _ => palette.primary.strong.color,
+    }
+}
+
+/// Text color of a collapsed row: log entries other than info are colored by level
+pub fn summary_color(payload: &Payload, theme: &Theme) -> Option<Color> {
+    let level = log_level(payload);
+    (kind(payload) == Some("log") && level_rank(&level) != 2).then(|| level_color(&level, theme))
+}
+
+/// The least severe log level the list shows, picked in the list header
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelFilter {
+    #[default]
+    All,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LevelFilter {
+    pub const ALL: [Self; 5] = [Self::All, Self::Debug, Self::Info, Self::Warn, Self::Error];
+
+    /// Whether the list shows a payload; only log entries are filtered
+    pub fn allows(self, payload: &Payload) -> bool {
+        let min_rank = match self {
+            Self::All => 0,
+            Self::Debug => 1,
+            Self::Info => 2,
+            Self::Warn => 3,
+            Self::Error => 4,
+        };
+        kind(payload) != Some("log") || level_rank(&log_level(payload)) >= min_rank
+    }
+}
+
+impl std::fmt::Display for LevelFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::All => "All levels",
+            Self::Debug => "Debug and up",
+            Self::Info => "Info and up",
+            Self::Warn => "Warn and up",
+            Self::Error => "Errors only",
+        })
+    }
+}
+
+fn log_view<'a>(payload: &Payload, theme: &Theme) -> Element<'a, Message> {
+    let level = log_level(payload);
+    let color = level_color(&level, theme);
+    row![
+        container(colored(level, 11.0, color))
+            .padding([1, 4])
+            .style(styles::container_badge),
+        text(log_message(&payload.value)).size(13),
+    ]
+    .spacing(10)
+    .align_y(Center)
+    .into()
+}
+
+/// Lays out an array of objects (or of arrays) as a grid of fixed-width cells
+fn table_view<'a>(payload: &Payload, theme: &Theme) -> Element<'a, Message> {
+    let rows = payload.value.as_array().map_or(&[][..], Vec::as_slice);
+
+    // Columns come from the `columns` option, or from the keys of the rows in order of appearance
+    let mut columns: Vec<String> = payload
+        .meta
+        .get("columns")
+        .and_then(Value::as_array)
+        .map(|columns| columns.iter().map(cell_text).collect())
+        .unwrap_or_default();
+    if columns.is_empty() {
+        for row in rows {
+            match row {
+                Value::Object(object) => {
+                    for key in object.keys() {
+                        if !columns.contains(key) {
+                            columns.push(key.clone());
+                        }
+                    }
+                }
+                Value::Array(cells) => {
+                    for idx in columns.len()..cells.len() {
+                        columns.push(idx.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let header_color = theme.extended_palette().secondary.base.text;
+    let cell = |content: String, color: Option<Color>| -> Element<'a, Message> {
+        container(text(content).size(12).style(move |_| text::Style { color }))
+            .width(Length::Fixed(CELL_WIDTH))
+            .padding([2, 5])
+            .into()
+    };
+
+    let header = row(columns.iter().map(|column| cell(column.clone(), Some(header_color))));
+    let body = rows.iter().enumerate().map(|(idx, row_value)| {
+        let cells = columns.iter().enumerate().map(|(column_idx, column)| {
+            let value = match row_value {
+                Value::Object(object) => object.get(column),
+                Value::Array(cells) => cells.get(column_idx),
+                other if column_idx == 0 => Some(other),
+                _ => None,
+            };
+            cell(value.map(cell_text).unwrap_or_default(), None)
+        });
+        let styled = container(row(cells));
+        if idx % 2 == 0 {
+            styled.style(styles::container_badge).into()
+        } else {
+            styled.into()
+        }
+    });
+
+    Column::with_children(std::iter::once(header.into()).chain(body)).spacing(2).into()
+}
+
+fn timer_view<'a>(payload: &Payload, theme: &Theme, aggregates: &Aggregates) -> Element<'a, Message> {
+    let name = name(payload);
+    let duration = timer_duration(&payload.value).unwrap_or_default();
+    let stats = aggregates.timers.get(&name).copied().unwrap_or_default();
+    let bar_color = theme.extended_palette().primary.strong.color;
+
+    // Bar length is relative to the slowest timer with the same name
+    let fraction = if stats.max_ms > 0.0 { duration / stats.max_ms } else { 0.0 };
+    let bar = container(horizontal_space())
+        .width(Length::Fixed((TIMER_BAR_WIDTH as f64 * fraction).max(2.0) as f32))
+        .height(6)
+        .style(move |_| container::Style {
+            background: Some(bar_color.into()),
+            border: iced_core::border::rounded(3),
+            ..container::Style::default()
+        });
+
+    let average = if stats.count > 0 { stats.total_ms / stats.count as f64 } else { 0.0 };
+    column![
+        row![text(name).size(14), text(format!("{duration:.2} ms")).size(14)].spacing(10),
+        bar,
+        text(format!(
+            "{} runs · avg {average:.2} ms · max {:.2} ms",
+            stats.count, stats.max_ms
+        ))
+        .size(11),
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn counter_view<'a>(payload: &Payload, aggregates: &Aggregates) -> Element<'a, Message> {
+    let name = name(payload);
+    let total = aggregates.counters.get(&name).copied().unwrap_or_default();
+    column![
+        text(format!("{name}: {total}")).size(18),
+        text(format!("this payload: {:+}", counter_increment(&payload.value))).size(11),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// A span's name, duration and fields, with the events and spans inside it indented below
+fn span_view<'a>(payload: &Payload, theme: &Theme, spans: &SpanTree, depth: usize) -> Element<'a, Message> {
+    let name = span_name(payload);
+    let fields = payload
+        .value
+        .get("fields")
+        .or_else(|| payload.value.get("attributes"))
+        .map(|fields| span_fields(fields, &[]))
+        .unwrap_or_default();
+    let muted = theme.extended_palette().background.strong.color;
+
+    let header = row![
+        text(name).size(14),
+        text(span_duration(&payload.value)).size(12),
+        colored(fields, 11.0, muted),
+    ]
+    .spacing(10)
+    .align_y(Center);
+
+    let children = spans.children(payload).map(|child| match kind(child) {
+        Some("span") if depth < MAX_SPAN_DEPTH => span_view(child, theme, spans, depth + 1),
+        Some("span") => text("…").into(),
+        _ => row![
+            log_view(child, theme),
+            colored(span_fields(&child.value, &["message"]), 11.0, muted),
+        ]
+        .spacing(10)
+        .align_y(Center)
+        .into(),
+    });
+
+    column![
+        header,
+        container(Column::with_children(children).spacing(4)).padding(iced_core::Padding {
+            left: SPAN_INDENT,
+            ..Default::default()
+        }),
+    ]
+    .spacing(4)
+    .into()
+}
+
+/// Renders a typed payload with its specialized view, or `None` for plain JSON
+pub fn view<'a>(
+    payload: &Payload,
+    theme: &Theme,
+    aggregates: &Aggregates,
+    spans: &SpanTree,
+) -> Option<Element<'a, Message>> {
+    match kind(payload)? {
+        "span" => Some(span_view(payload, theme, spans, 0)),
+        "log" => Some(log_view(payload, theme)),
+        "table" => Some(table_view(payload, theme)),
+        "exception" => Some(exception_summary(&payload.value, theme)),
+        "timer" => Some(timer_view(payload, theme, aggregates)),
+        "counter" => Some(counter_view(payload, aggregates)),
+        _ => None,
+    }
+}
@@ -1,27 +1,36 @@
 use iced::event::Event;
-use iced::keyboard::key;
 use iced::widget::scrollable::AbsoluteOffset;
-use iced::{keyboard, window, Length, Theme};
+use iced::{keyboard, window, Length, Point, Size, Theme};
 
 use crate::components;
 use crate::components::styles;
-use crate::app::{App, Message};
+use crate::components::typed::{LevelFilter, SpanTree};
+use crate::app::{App, Message, RowScroll};
 use crate::app::Message::Server;
-use crate::server;
-use crate::server::ServerMessage;
-use crate::settings::Settings;
-use iced::widget::{self, button, column, container, horizontal_space, row, svg, text};
-use iced::{Bottom, Element, Fill, Font, Subscription, Task};
+use dbug::server;
+use dbug::server::ServerMessage;
+use dbug::storage::{self, Payload, Storage};
+use dbug::settings::{
+    default_unix_socket_path, HotkeyAction, NewPayloadBehavior, SerializablePoint, SerializableSize, Settings,
+    DEFAULT_MAX_BODY_BYTES, DEFAULT_SERVER_HOST, DEFAULT_SERVER_PORT,
+};
+use iced::widget::{self, button, column, container, horizontal_space, pick_list, row, svg, text, tooltip};
+use iced::{Bottom, Center, Element, Fill, Font, Subscription, Task};
+use std::collections::HashSet;
 
 use global_hotkey::GlobalHotKeyEvent;
-use iced::futures::SinkExt;
+use iced::futures::{SinkExt, StreamExt};
 use iced::stream;
-use crate::app::HotkeyAction;
+use crate::hotkeys;
+use crate::instance;
+use crate::keymap::{self, KeyAction};
 
 const APP_TITLE: &str = concat!("dbug desktop v", env!("CARGO_PKG_VERSION"));
 
 pub fn gui() -> iced::Result {
     let settings = Settings::load();
+    let size = settings.get_window_size();
+    let position = settings.get_window_position();
 
     iced::application(App::default, App::update, App::view)
         .title(APP_TITLE)
@@ -30,13 +39,18 @@ pub fn gui() -> iced::Result {
         .default_font(Font::MONOSPACE)
         .theme(App::theme)
         .window(window::Settings {
-            size: settings.get_window_size(),
-            position: window::Position::Specific(settings.get_window_position()),
+            size: Size::new(size.width, size.height),
+            position: window::Position::Specific(Point::new(position.x, position.y)),
             ..window::Settings::default()
         })
         .run()
 }
 
+/// Changes made to the stored payloads, e.g. by `dbug send` run while the server was down
+fn storage_changes() -> impl futures::Stream<Item = ()> {
+    futures::stream::iter(Storage::default_dir().ok()).flat_map(storage::watch)
+}
+
 fn hotkey_listener() -> impl futures::Stream<Item = Message> {
     stream::channel(32, |mut sender: futures::channel::mpsc::Sender<Message>| async move {
         let receiver = GlobalHotKeyEvent::receiver();
@@ -56,10 +70,16 @@ impl App {
         Subscription::batch(vec![
             Subscription::run(server::listen).map(Server),
             Subscription::run(hotkey_listener),
-            iced::event::listen_with(|event, _status, window_id| {
+            Subscription::run(instance::forwarded).map(Message::Forwarded),
+            Subscription::run(storage_changes).map(|()| Message::CheckStorage),
+            iced::event::listen_with(|event, status, window_id| {
                 match event {
-                    // Forward Keyboard events
-                    Event::Keyboard(_) => Some(Message::Event(event)),
+                    // Forward key presses unless a text input took them for typing
+                    Event::Keyboard(keyboard::Event::KeyPressed { ref key, modifiers, .. })
+                        if status == iced::event::Status::Ignored || keymap::works_while_typing(key, modifiers) =>
+                    {
+                        Some(Message::Event(event))
+                    }
                     // Keep existing Window event handlers
                     Event::Window(window::Event::Closed) => Some(Message::WindowClosed),
                     Event::Window(window::Event::Moved(position)) => Some(Message::WindowMoved(position)),
@@ -74,27 +94,79 @@ impl App {
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Server(server_message) => {
-                match server_message {
-                    ServerMessage::PayloadReceived(value) => {
-                        let scroll_command;
-                        if let Err(e) = self.storage.add_json(&value) {
-                            eprintln!("Failed to store payload: {e}");
-                            scroll_command = Task::none();
-                        } else {
-                            self.payload_list_cache = self.storage.get_all();
-                            self.expanded_payload_id = self.payload_list_cache.first().map(|(id, _)| id.clone());
-                            self.collapsed_json_lines.clear();
-
-                            scroll_command = widget::scrollable::scroll_to::<Message>(
-                                widget::scrollable::Id::new("payload_scroll"),
-                                AbsoluteOffset { x: 0.0, y: 0.0 },
-                            );
-                        }
-                        scroll_command
+                let stored = match server_message {
+                    // An update in place keeps the selection unless the payload moves to the top
+                    ServerMessage::PayloadReceived(payload)
+                        if !self.settings.is_move_updated_to_top() && self.is_stored_client_id(&payload) =>
+                    {
+                        let updated = self.storage.add_payload(payload).map(|stored| stored.is_some());
+                        self.refresh_payloads(updated);
+                        return Task::none();
+                    }
+                    ServerMessage::PayloadReceived(payload) => self.storage.add_payload(payload).map(|_| ()),
+                    ServerMessage::PayloadsReceived(payloads) => self.storage.add_payloads(payloads).map(|_| ()),
+                    ServerMessage::ExchangeRecorded { request, response } => {
+                        self.storage.add_exchange(&request, &response).map(|_| ())
+                    }
+                    // Updates to existing payloads refresh the list without moving the selection
+                    ServerMessage::MetaUpdated { client_id, meta } => {
+                        let updated = self.storage.update_by_client_id(&client_id, |payload| {
+                            payload.meta.extend(meta.clone());
+                        });
+                        self.refresh_payloads(updated);
+                        return Task::none();
+                    }
+                    ServerMessage::PayloadRemoved { client_id } => {
+                        let removed = self.storage.delete_by_client_id(&client_id);
+                        self.refresh_payloads(removed);
+                        return Task::none();
+                    }
+                    ServerMessage::ClearRequested => return self.update(Message::ClearPayloads),
+                    ServerMessage::RequestRejected(reason) => {
+                        self.rejected_requests += 1;
+                        self.last_rejection = Some(reason);
+                        return Task::none();
+                    }
+                };
+
+                match stored {
+                    Err(e) => {
+                        eprintln!("Failed to store payload: {e}");
+                        Task::none()
                     }
+                    Ok(()) if self.paused || self.focus_locked => {
+                        self.hold_new_payloads();
+                        Task::none()
+                    }
+                    Ok(()) => match self.settings.get_new_payload_behavior() {
+                        NewPayloadBehavior::ExpandNewest => self.show_newest_payload(),
+                        NewPayloadBehavior::ExpandIfNone if self.expanded_payload_id.is_none() => {
+                            self.show_newest_payload()
+                        }
+                        NewPayloadBehavior::KeepSelection | NewPayloadBehavior::ExpandIfNone => {
+                            self.show_keeping_selection()
+                        }
+                    },
                 }
             }
-   
+            // Another process, e.g. `dbug send` run while the server was down, changed the stored payloads
+            Message::CheckStorage => match self.storage.reload_if_changed() {
+                Ok(true) if self.paused || self.focus_locked => {
+                    self.hold_new_payloads();
+                    Task::none()
+                }
+                Ok(true) => self.show_keeping_selection(),
+                Ok(false) => Task::none(),
+                Err(e) => {
+                    eprintln!("WARN: Failed to check for stored changes: {e}");
+                    Task::none()
+                }
+            },
+            Message::TogglePause => {
+                self.paused = !self.paused;
+                Task::none()
+            }
+            Message::ShowHeldPayloads => self.show_newest_payload(),
             Message::ShowModal => {
                 self.show_modal = true;
                 Task::none()
@@ -105,7 +177,7 @@ impl App {
             }
             Message::ThemeChanged(index) => {
                 if let Some(theme) = Theme::ALL.get(index).cloned() {
-                    self.settings.set_theme(theme);
+                    self.settings.set_theme_name(theme.to_string());
                     if let Err(e) = self.settings.save() {
                         eprintln!("Failed to save settings: {e}");
                     }
@@ -115,7 +187,11 @@ impl App {
             Message::TogglePayload(id) => {
                 if self.expanded_payload_id.as_ref() == Some(&id) {
                     self.expanded_payload_id = None;
+                    self.focus_locked = false;
                 } else {
+                    // Only expanding the newest payload would take the inspected one away, so only
+                    // then does inspecting hold new arrivals until it is collapsed
+                    self.focus_locked = self.settings.get_new_payload_behavior() == NewPayloadBehavior::ExpandNewest;
                     if let Some(_old_id) = self.expanded_payload_id.take() {
                     }
                     self.expanded_payload_id = Some(id.clone());
@@ -140,10 +216,15 @@ impl App {
                 if let Err(e) = self.storage.delete_all() {
                     eprintln!("Failed to clear payloads: {e}");
                 } else {
-                    self.payload_list_cache.clear();
+                    self.held_payload_ids.clear();
+                    self.set_payload_list(self.storage.get_all());
+                    self.selected_payload_id = None;
+                    self.focus_locked = false;
                     self.expanded_payload_id = None;
                     self.collapsed_json_lines.clear();
                     self.search_query.clear(); // Clear search when all payloads are cleared
+                    self.rejected_requests = 0;
+                    self.last_rejection = None;
                 }
                 Task::none()
             }
@@ -154,45 +235,63 @@ impl App {
                 });
 
                 if deleted {
-                    self.payload_list_cache.retain(|(item_id, _)| item_id != &id);
+                    self.set_payload_list(self.visible_payloads());
+                    self.held_payload_ids.remove(&id);
                     if self.expanded_payload_id.as_ref() == Some(&id) {
                         self.expanded_payload_id = None;
+                        self.focus_locked = false;
                         self.search_query.clear(); 
                     }
                     self.collapsed_json_lines.clear();
                 }
                 Task::none()
             }
-            Message::Event(event) => match event {
-                Event::Keyboard(keyboard::Event::KeyPressed {
-                    key: keyboard::Key::Named(key::Named::Tab),
-                    modifiers,
-                    ..
-                }) => {
-                    if modifiers.shift() {
-                        widget::focus_previous()
-                    } else {
-                        widget::focus_next()
-                    }
+            Message::Event(Event::Keyboard(keyboard::Event::KeyPressed { key, physical_key, modifiers, .. }))
+                if self.capturing_hotkey.is_some() =>
+            {
+                self.record_hotkey(&key, &physical_key, modifiers);
+                Task::none()
+            }
+            Message::Event(Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. })) => {
+                match keymap::action_for(&key, modifiers) {
+                    // The settings modal covers the list, so only its own shortcuts apply
+                    Some(action) if self.show_modal && action.is_list_action() => Task::none(),
+                    Some(action) => self.perform_key_action(action),
+                    None => Task::none(),
                 }
-                Event::Keyboard(keyboard::Event::KeyPressed {
-                    key: keyboard::Key::Named(key::Named::Escape),
-                    ..
-                }) => {
-                    self.hide_modal();
-                    Task::none()
+            }
+            Message::Event(_) => Task::none(),
+            Message::PayloadRowsMeasured(rows, scroll) => {
+                let list = widget::scrollable::Id::new(components::PAYLOAD_SCROLL_ID);
+                match scroll {
+                    RowScroll::Reveal(id) => {
+                        let Some((top, bottom)) = rows.bounds(&id) else {
+                            return Task::none();
+                        };
+                        let y = if top < rows.offset {
+                            top
+                        } else if bottom > rows.offset + rows.height {
+                            bottom - rows.height
+                        } else {
+                            return Task::none();
+                        };
+                        widget::scrollable::scroll_to::<Message>(list, AbsoluteOffset { x: 0.0, y })
+                    }
+                    RowScroll::Shift(ids) => {
+                        let y = ids.iter().filter_map(|id| rows.pitch(id)).sum::<f32>();
+                        widget::scrollable::scroll_by::<Message>(list, AbsoluteOffset { x: 0.0, y })
+                    }
                 }
-                Event::Keyboard(keyboard::Event::KeyPressed {
-                    key,
-                    modifiers,
-                    ..
-                }) if modifiers.command() && key == keyboard::Key::Character(",".into()) => {
-                    Task::perform(async {}, |()| Message::OpenSettings)
+            }
+            Message::TogglePin(id) => {
+                match self.storage.toggle_pin(&id) {
+                    Ok(_) => self.set_payload_list(self.visible_payloads()),
+                    Err(e) => eprintln!("Failed to pin payload: {e}"),
                 }
-                _ => Task::none(),
-            },
+                Task::none()
+            }
             Message::WindowMoved(position) => {
-                self.settings.set_window_position(position);
+                self.settings.set_window_position(SerializablePoint { x: position.x, y: position.y });
                 // Save immediately on move
                 if let Err(e) = self.settings.save() {
                     eprintln!("ERROR: Failed to save settings on move: {e}");
@@ -200,7 +299,7 @@ impl App {
                 Task::none()
             }
             Message::WindowResized(size) => {
-                self.settings.set_window_size(size);
+                self.settings.set_window_size(SerializableSize { width: size.width, height: size.height });
                  // Save immediately on resize
                 if let Err(e) = self.settings.save() {
                     eprintln!("ERROR: Failed to save settings on resize: {e}");
@@ -217,20 +316,61 @@ impl App {
                 self.search_query = query;
                 Task::none()
             }
-            Message::HotkeyActivated(id) => {
-                if let Some(action) = self.hotkey_actions.get(&id) {
-                    match action {
-                        HotkeyAction::ShowWindow => {
-                            window::gain_focus(self.main_window_id.unwrap())
-                        }
-                        HotkeyAction::ClearPayloads => {
-                            Task::perform(async {}, |()| Message::ClearPayloads)
-                        }
-                    }
-                } else {
+            Message::LevelFilterChanged(level_filter) => {
+                self.level_filter = level_filter;
+                self.set_payload_list(self.visible_payloads());
+                let expanded_shown = self.expanded_payload_id.as_ref().is_some_and(|id| {
+                    self.payload_list_cache.iter().any(|payload| &payload.id == id)
+                });
+                if !expanded_shown {
+                    self.expanded_payload_id = None;
+                    self.focus_locked = false;
+                    self.collapsed_json_lines.clear();
+                }
+                Task::none()
+            }
+            // Another launch handed over its command line: import its files and come to the front
+            Message::Forwarded(forwarded) => {
+                let imports: Vec<_> = forwarded
+                    .messages()
+                    .into_iter()
+                    .map(|message| self.update(Server(message)))
+                    .collect();
+                let focus = self.main_window_id.map_or_else(Task::none, window::gain_focus);
+                Task::batch(imports.into_iter().chain([focus]))
+            }
+            Message::HotkeyActivated(id) => match self.hotkeys.action_for(id) {
+                Some(HotkeyAction::ShowWindow) => self.main_window_id.map_or_else(Task::none, window::gain_focus),
+                Some(HotkeyAction::ClearPayloads) => Task::perform(async {}, |()| Message::ClearPayloads),
+                Some(HotkeyAction::TogglePause) => self.update(Message::TogglePause),
+                // The latest payload may be held out of the list, so ask storage rather than the cache
+                Some(HotkeyAction::CopyLatestPayload) => match self.storage.get_all().first() {
+                    Some(payload) => iced::clipboard::write(components::payload_json(payload)),
+                    None => Task::none(),
+                },
+                Some(HotkeyAction::TogglePinLatest) => match self.storage.get_all().first() {
+                    Some(payload) => self.update(Message::TogglePin(payload.id.clone())),
+                    None => Task::none(),
+                },
+                None => {
                     eprintln!("WARN: HotkeyActivated received for unknown ID: {id}");
                     Task::none()
                 }
+            },
+            Message::CaptureHotkey(action) => {
+                self.capturing_hotkey = Some(action);
+                self.hotkey_error = None;
+                Task::none()
+            }
+            Message::ClearHotkey(action) => {
+                self.hotkeys.unregister(action);
+                self.settings.set_hotkey(action, None);
+                self.capturing_hotkey = None;
+                self.hotkey_error = None;
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
             }
             Message::CaptureWindowId(id) => {
                 if self.main_window_id.is_none() {
@@ -258,9 +398,92 @@ impl App {
                 }
                 Task::none()
             }
+            Message::TcpPortChanged(port_str) => {
+                // An empty field disables the listener
+                if port_str.is_empty() {
+                    self.settings.set_tcp_port(None);
+                } else if let Ok(port) = port_str.parse::<u16>() {
+                    self.settings.set_tcp_port(Some(port));
+                } else {
+                    return Task::none();
+                }
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
+            }
+            Message::UdpPortChanged(port_str) => {
+                if port_str.is_empty() {
+                    self.settings.set_udp_port(None);
+                } else if let Ok(port) = port_str.parse::<u16>() {
+                    self.settings.set_udp_port(Some(port));
+                } else {
+                    return Task::none();
+                }
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
+            }
+            Message::UnixSocketToggled(enabled) => {
+                self.settings.set_unix_socket_enabled(enabled);
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
+            }
+            Message::UnixSocketPathChanged(path) => {
+                self.settings.set_unix_socket_path(path.into());
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
+            }
+            Message::MaxBodySizeChanged(kib_str) => {
+                // A zero limit would refuse every body, and an overflowing one is a typo
+                let max_body_bytes = kib_str
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|kib| *kib >= 1)
+                    .and_then(|kib| kib.checked_mul(1024));
+                if let Some(max_body_bytes) = max_body_bytes {
+                    self.settings.set_max_body_bytes(max_body_bytes);
+                    if let Err(e) = self.settings.save() {
+                        eprintln!("Failed to save settings: {e}");
+                    }
+                }
+                Task::none()
+            }
+            Message::MoveUpdatedToTopToggled(move_to_top) => {
+                self.settings.set_move_updated_to_top(move_to_top);
+                self.storage.set_move_updated_to_top(move_to_top);
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                Task::none()
+            }
+            Message::NewPayloadBehaviorChanged(behavior) => {
+                self.settings.set_new_payload_behavior(behavior);
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+                // The other behaviors keep the expanded payload, so payloads held for it can be shown
+                if behavior != NewPayloadBehavior::ExpandNewest && self.focus_locked {
+                    self.focus_locked = false;
+                    if !self.paused {
+                        return self.show_keeping_selection();
+                    }
+                }
+                Task::none()
+            }
             Message::ResetServerToDefaults => {
-                self.settings.set_server_host("127.0.0.1".to_string());
-                self.settings.set_server_port(53821);
+                self.settings.set_server_host(DEFAULT_SERVER_HOST.to_string());
+                self.settings.set_server_port(DEFAULT_SERVER_PORT);
+                self.settings.set_max_body_bytes(DEFAULT_MAX_BODY_BYTES);
+                self.settings.set_tcp_port(None);
+                self.settings.set_udp_port(None);
+                self.settings.set_unix_socket_enabled(false);
+                self.settings.set_unix_socket_path(default_unix_socket_path());
                 if let Err(e) = self.settings.save() {
                     eprintln!("Failed to save settings: {e}");
                 }
@@ -272,9 +495,17 @@ impl App {
         }
     }
 
-    /// Returns the current theme
+    /// Returns the current theme, found by the name saved in the settings
     fn theme(&self) -> Theme {
-        self.settings.theme()
+        let theme_name = self.settings.get_theme_name();
+        Theme::ALL
+            .iter()
+            .find(|theme| theme.to_string() == theme_name)
+            .cloned()
+            .unwrap_or_else(|| {
+                eprintln!("Theme '{theme_name}' not found, using Dark");
+                Theme::Dark
+            })
     }
 
     /// Renders the application view
@@ -300,12 +531,51 @@ impl App {
         .width(Fill)
         .height(Fill);
 
+        let pause_svg = svg(svg::Handle::from_memory(if self.paused {
+            include_bytes!("../assets/icons/mdi--play.svg").as_slice()
+        } else {
+            include_bytes!("../assets/icons/mdi--pause.svg").as_slice()
+        }))
+        .style(styles::svg_style_secondary)
+        .width(Fill)
+        .height(Fill);
+
         let button_size = 25;
         let payload_count = self.payload_list_cache.len();
 
-        // Calculate max height based on window size BEFORE the macro call
-        let window_size = self.settings.get_window_size();
-        let max_payload_height = window_size.height - 100.0;
+        let rejected: Element<'_, Message> = if self.rejected_requests > 0 {
+            tooltip(
+                text(format!("{} rejected", self.rejected_requests))
+                    .size(12)
+                    .style(text::danger),
+                container(text(self.last_rejection.clone().unwrap_or_default()).size(12))
+                    .padding(5)
+                    .style(container::rounded_box),
+                tooltip::Position::Bottom,
+            )
+            .into()
+        } else {
+            horizontal_space().width(0).into()
+        };
+
+        let held_banner: Element<'_, Message> = if self.held_payload_ids.is_empty() {
+            horizontal_space().height(0).into()
+        } else {
+            container(
+                button(
+                    text(format!("{} new — click to show", self.held_payload_ids.len()))
+                        .size(12)
+                        .width(Fill)
+                        .align_x(Center),
+                )
+                .width(Fill)
+                .padding(4)
+                .on_press(Message::ShowHeldPayloads),
+            )
+            .padding([0, 10])
+            .into()
+        };
+
 
         let content = container(
             column![
@@ -315,8 +585,18 @@ impl App {
                         .height(button_size)
                         .padding(3.0),
                     horizontal_space(),
+                    rejected,
+                    pick_list(LevelFilter::ALL, Some(self.level_filter), Message::LevelFilterChanged)
+                        .text_size(12)
+                        .padding([2, 6]),
                     text(format!("{payload_count}"))
                         .size(14),
+                    button(pause_svg)
+                        .style(button::secondary)
+                        .width(button_size)
+                        .height(button_size)
+                        .padding(3.0)
+                        .on_press(Message::TogglePause),
                     button(remove_all_svg)
                         .style(button::danger)
                         .width(button_size)
@@ -335,12 +615,15 @@ impl App {
                 .align_y(iced::alignment::Vertical::Center)
                 .height(Length::Shrink),
 
+                held_banner,
                 components::payload_list(
                     &self.payload_list_cache,
+                    &self.span_tree,
                     self.expanded_payload_id.as_ref(),
+                    self.selected_payload_id.as_ref(),
                     &self.theme(),
                     &self.collapsed_json_lines,
-                    max_payload_height,
+                    self.max_payload_height(),
                     &self.search_query,
                 ),
                 row![horizontal_space()]
@@ -352,7 +635,13 @@ impl App {
 
         if self.show_modal {
             let current_theme = self.theme();
-            let settings_content = components::settings_modal(current_theme, &self.settings);
+            let settings_content = components::settings_modal(
+                current_theme,
+                &self.settings,
+                &self.hotkeys,
+                self.capturing_hotkey,
+                self.hotkey_error.clone(),
+            );
 
             components::modal(content, settings_content, Message::HideModal)
         } else {
@@ -360,7 +649,245 @@ impl App {
         }
     }
 
+    /// Binds the hotkey being captured to a key press, refusing combinations already in use
+    fn record_hotkey(&mut self, key: &keyboard::Key, physical_key: &keyboard::key::Physical, modifiers: keyboard::Modifiers) {
+        let Some(action) = self.capturing_hotkey else {
+            return;
+        };
+        if *key == keyboard::Key::Named(keyboard::key::Named::Escape) {
+            self.capturing_hotkey = None;
+            return;
+        }
+
+        let shortcut = match hotkeys::shortcut_from_key(physical_key, modifiers) {
+            Ok(Some(shortcut)) => shortcut,
+            // Only modifiers so far; wait for the rest of the combination
+            Ok(None) => return,
+            Err(e) => {
+                self.capturing_hotkey = None;
+                self.hotkey_error = Some(e);
+                return;
+            }
+        };
+        self.capturing_hotkey = None;
+
+        let configured_conflict = self
+            .settings
+            .get_hotkeys()
+            .iter()
+            .find(|(other, other_shortcut)| **other != action && hotkeys::same_shortcut(other_shortcut, &shortcut));
+        let conflict = match configured_conflict {
+            Some((other, _)) => Some(other.description()),
+            None => keymap::description_for(key, modifiers),
+        };
+        if let Some(description) = conflict {
+            self.hotkey_error = Some(format!(
+                "{} is already used to {}",
+                hotkeys::display(&shortcut),
+                description.to_lowercase()
+            ));
+            return;
+        }
+
+        match self.hotkeys.register(action, &shortcut) {
+            Ok(()) => {
+                self.hotkey_error = None;
+                self.settings.set_hotkey(action, Some(shortcut));
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Failed to save settings: {e}");
+                }
+            }
+            Err(e) => self.hotkey_error = Some(e),
+        }
+    }
+
+    /// Tallest an expanded payload may grow, based on the window size
+    fn max_payload_height(&self) -> f32 {
+        self.settings.get_window_size().height - 100.0
+    }
+
+    /// Index of the selected payload, falling back to the expanded one
+    fn selected_index(&self) -> Option<usize> {
+        let id = self.selected_payload_id.as_ref().or(self.expanded_payload_id.as_ref())?;
+        self.payload_list_cache.iter().position(|payload| &payload.id == id)
+    }
+
+    /// Selects the payload at `index` and scrolls just enough to bring it into view
+    fn select_index(&mut self, index: usize) -> Task<Message> {
+        let Some(payload) = self.payload_list_cache.get(index) else {
+            return Task::none();
+        };
+        self.selected_payload_id = Some(payload.id.clone());
+        self.scroll_rows(RowScroll::Reveal(payload.id.clone()))
+    }
+
+    /// Measures the rows of the list as laid out, then scrolls it as `scroll` asks
+    fn scroll_rows(&self, scroll: RowScroll) -> Task<Message> {
+        components::measure_rows(self.payload_list_cache.iter().map(|payload| payload.id.clone()))
+            .map(move |rows| Message::PayloadRowsMeasured(rows, scroll.clone()))
+    }
+
+    /// Runs a keyboard shortcut from the keymap
+    fn perform_key_action(&mut self, action: KeyAction) -> Task<Message> {
+        let selected = self.selected_index();
+        let selected_id = selected.map(|index| self.payload_list_cache[index].id.clone());
+        let last_index = self.payload_list_cache.len().saturating_sub(1);
+        match action {
+            KeyAction::FocusNext => widget::focus_next(),
+            KeyAction::FocusPrevious => widget::focus_previous(),
+            KeyAction::CloseModal => {
+                self.hide_modal();
+                Task::none()
+            }
+            KeyAction::OpenSettings => Task::perform(async {}, |()| Message::OpenSettings),
+            KeyAction::SelectPrevious => self.select_index(selected.map_or(0, |index| index.saturating_sub(1))),
+            KeyAction::SelectNext => self.select_index(selected.map_or(0, |index| (index + 1).min(last_index))),
+            KeyAction::SelectFirst => self.select_index(0),
+            KeyAction::SelectLast => self.select_index(last_index),
+            KeyAction::ToggleExpanded => match selected_id {
+                Some(id) => self.update(Message::TogglePayload(id)),
+                None => Task::none(),
+            },
+            KeyAction::Delete => {
+                let (Some(index), Some(id)) = (selected, selected_id) else {
+                    return Task::none();
+                };
+                let task = self.update(Message::DeletePayload(id));
+                // Keep the selection at the same place in the list
+                self.selected_payload_id = None;
+                let next = index.min(self.payload_list_cache.len().saturating_sub(1));
+                task.chain(self.select_index(next))
+            }
+            KeyAction::Copy => match selected.map(|index| &self.payload_list_cache[index]) {
+                Some(payload) => iced::clipboard::write(components::payload_json(payload)),
+                None => Task::none(),
+            },
+            KeyAction::Search => {
+                let Some(id) = selected_id else {
+                    return Task::none();
+                };
+                // The search field lives in the expanded payload's header
+                let expand = if self.expanded_payload_id.as_ref() == Some(&id) {
+                    Task::none()
+                } else {
+                    self.update(Message::TogglePayload(id))
+                };
+                expand.chain(widget::text_input::focus(widget::text_input::Id::new(components::SEARCH_INPUT_ID)))
+            }
+            KeyAction::TogglePin => match selected_id {
+                Some(id) => self.update(Message::TogglePin(id)),
+                None => Task::none(),
+            },
+        }
+    }
+
+    /// Whether the payload carries a client ID that an already stored payload has
+    fn is_stored_client_id(&self, payload: &Payload) -> bool {
+        payload.client_id.as_ref().is_some_and(|client_id| {
+            self.shown_payloads().any(|stored| stored.client_id.as_ref() == Some(client_id))
+        })
+    }
+
+    /// Replaces the list, leaving out log levels the filter hides and moving events and child spans
+    /// into the span they belong to
+    fn set_payload_list(&mut self, mut payloads: Vec<Payload>) {
+        payloads.retain(|payload| self.level_filter.allows(payload));
+        (self.payload_list_cache, self.span_tree) = SpanTree::split(payloads);
+    }
+
+    /// Payloads in the list, whether as rows or inside a span
+    fn shown_payloads(&self) -> impl Iterator<Item = &Payload> {
+        self.payload_list_cache.iter().chain(self.span_tree.nested())
+    }
+
+    /// Stored payloads except those held out of the list
+    fn visible_payloads(&self) -> Vec<Payload> {
+        let mut payloads = self.storage.get_all();
+        payloads.retain(|payload| !self.held_payload_ids.contains(&payload.id));
+        payloads
+    }
+
+    /// Holds payloads that are not in the list yet, leaving the view as it is
+    fn hold_new_payloads(&mut self) {
+        let all = self.storage.get_all();
+        for payload in &all {
+            // Payloads the level filter hides are not waiting to be shown
+            if self.level_filter.allows(payload) && !self.shown_payloads().any(|shown| shown.id == payload.id) {
+                self.held_payload_ids.insert(payload.id.clone());
+            }
+        }
+        // Forget held payloads that were evicted before being shown
+        self.held_payload_ids.retain(|id| all.iter().any(|payload| &payload.id == id));
+        self.set_payload_list(self.visible_payloads());
+    }
+
+    /// Releases any held payloads, then expands the newest one and scrolls to it
+    fn show_newest_payload(&mut self) -> Task<Message> {
+        self.held_payload_ids.clear();
+        self.focus_locked = false;
+        self.set_payload_list(self.storage.get_all());
+        self.expanded_payload_id = self.payload_list_cache.first().map(|payload| payload.id.clone());
+        self.collapsed_json_lines.clear();
+
+        widget::scrollable::scroll_to::<Message>(
+            widget::scrollable::Id::new(components::PAYLOAD_SCROLL_ID),
+            AbsoluteOffset { x: 0.0, y: 0.0 },
+        )
+    }
+
+    /// Shows new payloads without changing the expanded one, scrolling so it stays in place
+    fn show_keeping_selection(&mut self) -> Task<Message> {
+        let rows_above = |payloads: &[Payload], id: &String| {
+            let index = payloads.iter().position(|payload| &payload.id == id)?;
+            Some(payloads[..index].iter().map(|payload| payload.id.clone()).collect::<Vec<_>>())
+        };
+        let expanded_id = self.expanded_payload_id.clone();
+        let old_above = expanded_id.as_ref().and_then(|id| rows_above(&self.payload_list_cache, id));
+
+        self.held_payload_ids.clear();
+        self.set_payload_list(self.storage.get_all());
+
+        let new_above = expanded_id.as_ref().and_then(|id| rows_above(&self.payload_list_cache, id));
+        match (old_above, new_above) {
+            // Rows added above the expanded payload push it down; scroll down by the space they take
+            (Some(old_above), Some(new_above)) => {
+                let old_above: HashSet<String> = old_above.into_iter().collect();
+                let added: Vec<String> = new_above.into_iter().filter(|id| !old_above.contains(id)).collect();
+                if added.is_empty() {
+                    Task::none()
+                } else {
+                    self.scroll_rows(RowScroll::Shift(added))
+                }
+            }
+            (_, None) => {
+                self.expanded_payload_id = None;
+                self.collapsed_json_lines.clear();
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    /// Reloads the cached list after an update to existing payloads
+    fn refresh_payloads(&mut self, result: std::io::Result<bool>) {
+        match result {
+            Ok(true) => {
+                self.set_payload_list(self.visible_payloads());
+                let expanded_exists = self.expanded_payload_id.as_ref().is_some_and(|id| {
+                    self.payload_list_cache.iter().any(|payload| &payload.id == id)
+                });
+                if !expanded_exists {
+                    self.expanded_payload_id = None;
+                    self.collapsed_json_lines.clear();
+                }
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to update payloads: {e}"),
+        }
+    }
+
     fn hide_modal(&mut self) {
         self.show_modal = false;
+        self.capturing_hotkey = None;
     }
 }
@@ -0,0 +1,121 @@
+use crate::instance;
+use dbug::server::{self, ServerMessage};
+use dbug::settings::Settings;
+use dbug::storage::{Payload, Storage};
+use futures::StreamExt;
+use std::io::{self, Write};
+
+/// How received payloads are written to stdout in headless mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One compact JSON object per line, including the payload's ID and metadata
+    Json,
+    /// The payload's value as indented JSON
+    Pretty,
+    /// Time and a single line of the payload's value
+    Summary,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "pretty" => Some(Self::Pretty),
+            "summary" => Some(Self::Summary),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn format(self, payload: &Payload) -> String {
+        match self {
+            Self::Json => serde_json::to_string(payload).unwrap_or_default(),
+            Self::Pretty => serde_json::to_string_pretty(&payload.value).unwrap_or_default(),
+            Self::Summary => {
+                let time = payload
+                    .received()
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map_or_else(String::new, |time| time.format("%H:%M:%S%.3f").to_string());
+                format!("{time} {}", payload.value)
+            }
+        }
+    }
+}
+
+/// Runs the server and storage without a window until SIGINT or SIGTERM
+pub fn run(format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut storage = Storage::new()?;
+        storage.set_move_updated_to_top(Settings::load().is_move_updated_to_top());
+        let mut messages = Box::pin(futures::stream::select(server::listen(), instance::imports()));
+        let mut shutdown = Box::pin(shutdown_signal());
+
+        loop {
+            tokio::select! {
+                () = &mut shutdown => break,
+                message = messages.next() => match message {
+                    Some(message) => handle(&storage, message, format),
+                    None => break,
+                },
+            }
+        }
+
+        // Every change is saved as it is stored; save once more so nothing is left behind
+        storage.flush()?;
+        eprintln!("INFO: Shutting down");
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+}
+
+/// Stores received payloads, then prints them as stored
+fn handle(storage: &Storage, message: ServerMessage, format: OutputFormat) {
+    if let ServerMessage::RequestRejected(reason) = &message {
+        eprintln!("WARN: Rejected request: {reason}");
+    }
+    match message.store(storage) {
+        Ok(stored) => print_payloads(&stored, format),
+        Err(e) => eprintln!("Failed to store payload: {e}"),
+    }
+}
+
+fn print_payloads(payloads: &[Payload], format: OutputFormat) {
+    let mut stdout = io::stdout().lock();
+    for payload in payloads {
+        let _ = writeln!(stdout, "{}", format.format(payload));
+    }
+    let _ = stdout.flush();
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM on Unix
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("WARN: Failed to listen for Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    {
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    eprintln!("WARN: Failed to listen for SIGTERM: {e}");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        tokio::select! {
+            () = interrupt => {}
+            () = terminate => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    interrupt.await;
+}
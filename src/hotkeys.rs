@@ -0,0 +1,189 @@
+use dbug::settings::HotkeyAction;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::GlobalHotKeyManager;
+use iced::keyboard::{key, Modifiers};
+use std::collections::HashMap;
+
+/// Turns a captured key press into a shortcut string, requiring at least one modifier
+///
+/// Returns `Ok(None)` for a modifier pressed on its own, while the user is still composing.
+pub fn shortcut_from_key(physical_key: &key::Physical, modifiers: Modifiers) -> Result<Option<String>, String> {
+    let key::Physical::Code(code) = physical_key else {
+        return Err("That key cannot be used for a hotkey".to_string());
+    };
+    if matches!(
+        code,
+        key::Code::ShiftLeft
+            | key::Code::ShiftRight
+            | key::Code::ControlLeft
+            | key::Code::ControlRight
+            | key::Code::AltLeft
+            | key::Code::AltRight
+            | key::Code::SuperLeft
+            | key::Code::SuperRight
+    ) {
+        return Ok(None);
+    }
+
+    let mut parts = Vec::new();
+    if modifiers.shift() {
+        parts.push("shift".to_string());
+    }
+    if modifiers.control() {
+        parts.push("control".to_string());
+    }
+    if modifiers.alt() {
+        parts.push("alt".to_string());
+    }
+    if modifiers.logo() {
+        parts.push("super".to_string());
+    }
+    if parts.is_empty() {
+        return Err("Global hotkeys need at least one modifier".to_string());
+    }
+    // iced and global-hotkey both name physical keys after the W3C codes, e.g. "KeyL"
+    parts.push(format!("{code:?}"));
+
+    let shortcut = parts.join("+");
+    shortcut
+        .parse::<HotKey>()
+        .map(|_| Some(shortcut.clone()))
+        .map_err(|e| format!("{shortcut} is not supported: {e}"))
+}
+
+/// Whether two shortcut strings describe the same key combination, e.g. "shift+super+KeyL" and "super+shift+L"
+pub fn same_shortcut(a: &str, b: &str) -> bool {
+    match (a.parse::<HotKey>(), b.parse::<HotKey>()) {
+        (Ok(a), Ok(b)) => a.id == b.id,
+        _ => false,
+    }
+}
+
+/// Formats a shortcut string for display, e.g. "shift+super+KeyL" as "Shift+Super+L"
+pub fn display(shortcut: &str) -> String {
+    shortcut
+        .split('+')
+        .map(|part| {
+            let part = part.strip_prefix("Key").or_else(|| part.strip_prefix("Digit")).unwrap_or(part);
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join("+")
+}
+
+/// Global hotkeys currently registered with the OS, by action
+///
+/// Works without a hotkey manager, e.g. on Wayland or in CI; every registration then fails and is recorded.
+pub struct HotkeyRegistry {
+    manager: Option<GlobalHotKeyManager>,
+    /// Why the hotkey manager could not be created
+    unavailable: Option<String>,
+    registered: HashMap<HotkeyAction, HotKey>,
+    /// Why the configured shortcut for an action is not registered
+    failed: HashMap<HotkeyAction, String>,
+}
+
+impl Default for HotkeyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HotkeyRegistry {
+    pub fn new() -> Self {
+        let (manager, unavailable) = match GlobalHotKeyManager::new() {
+            Ok(manager) => (Some(manager), None),
+            Err(e) => {
+                eprintln!("WARN: Global hotkeys are unavailable: {e}");
+                (None, Some(e.to_string()))
+            }
+        };
+        Self {
+            manager,
+            unavailable,
+            registered: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    /// Why global hotkeys cannot be used at all, if they cannot
+    pub fn unavailable_reason(&self) -> Option<&str> {
+        self.unavailable.as_deref()
+    }
+
+    /// Why the configured shortcut for `action` is not registered, if it is not
+    pub fn failure(&self, action: HotkeyAction) -> Option<&str> {
+        self.failed.get(&action).map(String::as_str)
+    }
+
+    /// Binds `action` to `shortcut`, replacing its previous binding
+    ///
+    /// The previous binding is restored if the new one cannot be registered.
+    pub fn register(&mut self, action: HotkeyAction, shortcut: &str) -> Result<(), String> {
+        let result = self.try_register(action, shortcut);
+        match &result {
+            Ok(()) => self.failed.remove(&action),
+            // A failed change leaves a previous, working binding in place
+            Err(e) if !self.registered.contains_key(&action) => self.failed.insert(action, e.clone()),
+            Err(_) => None,
+        };
+        result
+    }
+
+    fn try_register(&mut self, action: HotkeyAction, shortcut: &str) -> Result<(), String> {
+        let Some(manager) = &self.manager else {
+            return Err("Global hotkeys are unavailable".to_string());
+        };
+        let hotkey = shortcut
+            .parse::<HotKey>()
+            .map_err(|e| format!("Invalid hotkey {shortcut}: {e}"))?;
+        if let Some((other, _)) = self
+            .registered
+            .iter()
+            .find(|(other, registered)| **other != action && registered.id == hotkey.id)
+        {
+            return Err(format!("{} is already used to {}", display(shortcut), other.description().to_lowercase()));
+        }
+
+        let previous = self.registered.remove(&action);
+        if let Some(previous) = previous {
+            if let Err(e) = manager.unregister(previous) {
+                eprintln!("WARN: Failed to unregister hotkey for {action:?}: {e}");
+            }
+        }
+        if let Err(e) = manager.register(hotkey) {
+            if let Some(previous) = previous {
+                if manager.register(previous).is_ok() {
+                    self.registered.insert(action, previous);
+                }
+            }
+            return Err(format!("Could not register {}: {e}", display(shortcut)));
+        }
+        self.registered.insert(action, hotkey);
+        Ok(())
+    }
+
+    /// Removes the binding for `action`, returning it if there was one
+    pub fn unregister(&mut self, action: HotkeyAction) -> Option<HotKey> {
+        self.failed.remove(&action);
+        let hotkey = self.registered.remove(&action)?;
+        if let Some(manager) = &self.manager {
+            if let Err(e) = manager.unregister(hotkey) {
+                eprintln!("WARN: Failed to unregister hotkey for {action:?}: {e}");
+            }
+        }
+        Some(hotkey)
+    }
+
+    /// The action bound to the hotkey with the given event ID
+    pub fn action_for(&self, id: u32) -> Option<HotkeyAction> {
+        self.registered
+            .iter()
+            .find(|(_, hotkey)| hotkey.id == id)
+            .map(|(action, _)| *action)
+    }
+}
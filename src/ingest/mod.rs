@@ -0,0 +1,6 @@
+//! Adapters that turn third-party wire formats into dbug payloads
+
+pub(crate) mod otlp;
+pub mod protocol;
+pub(crate) mod ray;
+pub(crate) mod sentry;
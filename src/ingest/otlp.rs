@@ -0,0 +1,330 @@
+use crate::storage::Payload;
+use serde_json::{json, Map, Value};
+
+/// Decodes an OTLP `AnyValue` into plain JSON
+fn any_value(value: &Value) -> Value {
+    if let Some(string) = value.get("stringValue") {
+        string.clone()
+    } else if let Some(boolean) = value.get("boolValue") {
+        boolean.clone()
+    } else if let Some(int) = value.get("intValue") {
+        // 64-bit integers are encoded as strings in OTLP/JSON
+        int.as_str()
+            .and_then(|int| int.parse::<i64>().ok())
+            .map_or_else(|| int.clone(), Value::from)
+    } else if let Some(double) = value.get("doubleValue") {
+        double.clone()
+    } else if let Some(bytes) = value.get("bytesValue") {
+        bytes.clone()
+    } else if let Some(array) = value.get("arrayValue") {
+        Value::Array(items(array, "values").iter().map(any_value).collect())
+    } else if let Some(kvlist) = value.get("kvlistValue") {
+        Value::Object(attributes(kvlist, "values"))
+    } else {
+        Value::Null
+    }
+}
+
+/// Returns the array under `key`, or an empty slice when missing
+fn items<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Flattens an OTLP `KeyValue` list into a JSON object
+fn attributes(value: &Value, key: &str) -> Map<String, Value> {
+    items(value, key)
+        .iter()
+        .filter_map(|attribute| {
+            let key = attribute.get("key")?.as_str()?;
+            Some((key.to_string(), attribute.get("value").map_or(Value::Null, any_value)))
+        })
+        .collect()
+}
+
+/// Parses a nanosecond timestamp, which OTLP/JSON may encode as a string or a number
+fn nanos(value: &Value, key: &str) -> Option<u64> {
+    let nanos = value.get(key)?;
+    nanos.as_u64().or_else(|| nanos.as_str()?.parse().ok())
+}
+
+/// Maps an OTLP severity number onto its short name
+fn severity_name(number: u64) -> &'static str {
+    match number {
+        1..=4 => "TRACE",
+        5..=8 => "DEBUG",
+        9..=12 => "INFO",
+        13..=16 => "WARN",
+        17..=20 => "ERROR",
+        21..=24 => "FATAL",
+        _ => "UNSPECIFIED",
+    }
+}
+
+/// Trace and span ids are empty strings when unset
+fn id(value: &Value, key: &str) -> Value {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+        .map_or(Value::Null, Value::from)
+}
+
+/// Inserts `value` under `key` unless it is an empty object
+fn insert_non_empty(object: &mut Map<String, Value>, key: &str, value: Map<String, Value>) {
+    if !value.is_empty() {
+        object.insert(key.to_string(), Value::Object(value));
+    }
+}
+
+/// Walks the resource/scope nesting shared by logs and traces
+fn for_each_record(
+    request: &Value,
+    resource_key: &str,
+    scope_key: &str,
+    record_key: &str,
+    mut f: impl FnMut(&Value, &Map<String, Value>, &str),
+) {
+    for resource in items(request, resource_key) {
+        let resource_attributes = resource
+            .get("resource")
+            .map(|resource| attributes(resource, "attributes"))
+            .unwrap_or_default();
+        for scope in items(resource, scope_key) {
+            let scope_name = scope
+                .get("scope")
+                .and_then(|scope| scope.get("name"))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            for record in items(scope, record_key) {
+                f(record, &resource_attributes, scope_name);
+            }
+        }
+    }
+}
+
+/// Unpacks an OTLP `ExportLogsServiceRequest` into one payload per log record
+pub(crate) fn logs(request: &Value) -> Vec<Payload> {
+    let mut payloads = Vec::new();
+    for_each_record(request, "resourceLogs", "scopeLogs", "logRecords", |record, resource, scope| {
+        let severity = record
+            .get("severityText")
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+            .or_else(|| record.get("severityNumber")?.as_u64().map(|n| severity_name(n).to_string()));
+
+        let mut value = Map::new();
+        value.insert("body".to_string(), record.get("body").map_or(Value::Null, any_value));
+        insert_non_empty(&mut value, "attributes", attributes(record, "attributes"));
+        insert_non_empty(&mut value, "resource", resource.clone());
+        if !scope.is_empty() {
+            value.insert("scope".to_string(), scope.into());
+        }
+
+        payloads.push(
+            Payload::new(Value::Object(value))
+                .with_meta("kind", "log")
+                .with_meta("severity", severity)
+                .with_meta("trace_id", id(record, "traceId"))
+                .with_meta("span_id", id(record, "spanId")),
+        );
+    });
+    payloads
+}
+
+/// Unpacks an OTLP `ExportTraceServiceRequest` into one payload per span
+pub(crate) fn traces(request: &Value) -> Vec<Payload> {
+    let mut payloads = Vec::new();
+    for_each_record(request, "resourceSpans", "scopeSpans", "spans", |span, resource, scope| {
+        let duration_ms = nanos(span, "startTimeUnixNano")
+            .zip(nanos(span, "endTimeUnixNano"))
+            .map(|(start, end)| end.saturating_sub(start) as f64 / 1_000_000.0);
+
+        let events = items(span, "events")
+            .iter()
+            .map(|event| {
+                json!({
+                    "name": event.get("name").cloned().unwrap_or(Value::Null),
+                    "attributes": attributes(event, "attributes"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut value = Map::new();
+        value.insert("name".to_string(), span.get("name").cloned().unwrap_or(Value::Null));
+        if let Some(duration_ms) = duration_ms {
+            value.insert("duration_ms".to_string(), duration_ms.into());
+        }
+        insert_non_empty(&mut value, "attributes", attributes(span, "attributes"));
+        if !events.is_empty() {
+            value.insert("events".to_string(), events.into());
+        }
+        if let Some(status) = span.get("status").filter(|status| status.as_object().is_some_and(|s| !s.is_empty())) {
+            value.insert("status".to_string(), status.clone());
+        }
+        insert_non_empty(&mut value, "resource", resource.clone());
+        if !scope.is_empty() {
+            value.insert("scope".to_string(), scope.into());
+        }
+
+        payloads.push(
+            Payload::new(Value::Object(value))
+                .with_meta("kind", "span")
+                .with_meta("trace_id", id(span, "traceId"))
+                .with_meta("span_id", id(span, "spanId"))
+                .with_meta("parent_span_id", id(span, "parentSpanId"))
+                .with_meta("duration_ms", duration_ms),
+        );
+    });
+    payloads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shaped like the examples in opentelemetry-proto, as an OpenTelemetry SDK exports them
+    const LOGS: &str = r#"{
+      "resourceLogs": [{
+        "resource": { "attributes": [{ "key": "service.name", "value": { "stringValue": "my.service" } }] },
+        "scopeLogs": [{
+          "scope": { "name": "my.library", "version": "1.0.0" },
+          "logRecords": [{
+            "timeUnixNano": "1544712660300000000",
+            "observedTimeUnixNano": "1544712660300000000",
+            "severityNumber": 10,
+            "severityText": "Information",
+            "traceId": "5b8efff798038103d269b633813fc60c",
+            "spanId": "eee19b7ec3c1b174",
+            "body": { "stringValue": "Example log record" },
+            "attributes": [
+              { "key": "string.attribute", "value": { "stringValue": "some string" } },
+              { "key": "boolean.attribute", "value": { "boolValue": true } },
+              { "key": "int.attribute", "value": { "intValue": "10" } },
+              { "key": "double.attribute", "value": { "doubleValue": 637.704 } },
+              { "key": "array.attribute", "value": { "arrayValue": { "values": [{ "stringValue": "many" }, { "stringValue": "values" }] } } },
+              { "key": "map.attribute", "value": { "kvlistValue": { "values": [{ "key": "some.map.key", "value": { "stringValue": "some value" } }] } } }
+            ]
+          }, {
+            "severityNumber": 17,
+            "traceId": "",
+            "spanId": "",
+            "body": { "stringValue": "No severity text" }
+          }]
+        }]
+      }]
+    }"#;
+
+    const TRACES: &str = r#"{
+      "resourceSpans": [{
+        "resource": { "attributes": [{ "key": "service.name", "value": { "stringValue": "my.service" } }] },
+        "scopeSpans": [{
+          "scope": { "name": "my.library", "version": "1.0.0" },
+          "spans": [{
+            "traceId": "5b8efff798038103d269b633813fc60c",
+            "spanId": "eee19b7ec3c1b174",
+            "parentSpanId": "eee19b7ec3c1b173",
+            "name": "GET /users",
+            "startTimeUnixNano": "1544712660000000000",
+            "endTimeUnixNano": 1544712661500000000,
+            "kind": 2,
+            "attributes": [{ "key": "http.response.status_code", "value": { "intValue": "200" } }],
+            "events": [{ "timeUnixNano": "1544712660500000000", "name": "cache miss", "attributes": [] }],
+            "status": { "code": 1 }
+          }, {
+            "traceId": "5b8efff798038103d269b633813fc60c",
+            "spanId": "eee19b7ec3c1b173",
+            "parentSpanId": "",
+            "name": "root",
+            "status": {}
+          }]
+        }]
+      }]
+    }"#;
+
+    #[test]
+    fn logs_unpack_each_record() {
+        let payloads = logs(&serde_json::from_str(LOGS).unwrap());
+        assert_eq!(payloads.len(), 2);
+
+        let record = &payloads[0];
+        assert_eq!(
+            record.value,
+            json!({
+                "body": "Example log record",
+                "attributes": {
+                    "string.attribute": "some string",
+                    "boolean.attribute": true,
+                    "int.attribute": 10,
+                    "double.attribute": 637.704,
+                    "array.attribute": ["many", "values"],
+                    "map.attribute": { "some.map.key": "some value" },
+                },
+                "resource": { "service.name": "my.service" },
+                "scope": "my.library",
+            })
+        );
+        assert_eq!(record.meta["kind"], "log");
+        assert_eq!(record.meta["severity"], "Information");
+        assert_eq!(record.meta["trace_id"], "5b8efff798038103d269b633813fc60c");
+        assert_eq!(record.meta["span_id"], "eee19b7ec3c1b174");
+
+        // Without a severity text the number is named, and empty ids are left out
+        assert_eq!(payloads[1].meta["severity"], "ERROR");
+        assert_eq!(payloads[1].meta.get("trace_id"), None);
+    }
+
+    #[test]
+    fn traces_unpack_each_span() {
+        let payloads = traces(&serde_json::from_str(TRACES).unwrap());
+        assert_eq!(payloads.len(), 2);
+
+        let span = &payloads[0];
+        assert_eq!(
+            span.value,
+            json!({
+                "name": "GET /users",
+                "duration_ms": 1500.0,
+                "attributes": { "http.response.status_code": 200 },
+                "events": [{ "name": "cache miss", "attributes": {} }],
+                "status": { "code": 1 },
+                "resource": { "service.name": "my.service" },
+                "scope": "my.library",
+            })
+        );
+        assert_eq!(span.meta["kind"], "span");
+        assert_eq!(span.meta["parent_span_id"], "eee19b7ec3c1b173");
+        assert_eq!(span.meta["duration_ms"], 1500.0);
+
+        // A span without timestamps has no duration, and an empty status is left out
+        assert_eq!(payloads[1].value.get("duration_ms"), None);
+        assert_eq!(payloads[1].value.get("status"), None);
+        assert_eq!(payloads[1].meta.get("parent_span_id"), None);
+    }
+
+    #[test]
+    fn malformed_requests_yield_nothing() {
+        for request in [
+            json!(null),
+            json!("resourceLogs"),
+            json!({ "resourceLogs": "not a list", "resourceSpans": {} }),
+            json!({ "resourceLogs": [{ "scopeLogs": [{ "logRecords": "not a list" }] }] }),
+            json!({ "resourceSpans": [{ "scopeSpans": [{ "spans": 42 }] }] }),
+        ] {
+            assert!(logs(&request).is_empty(), "{request}");
+            assert!(traces(&request).is_empty(), "{request}");
+        }
+    }
+
+    #[test]
+    fn malformed_values_decode_to_what_they_carry() {
+        assert_eq!(any_value(&json!({ "intValue": "not a number" })), json!("not a number"));
+        assert_eq!(any_value(&json!({ "unknownValue": 1 })), Value::Null);
+        assert_eq!(any_value(&json!({ "arrayValue": { "values": "not a list" } })), json!([]));
+        let record = json!({ "attributes": [{ "value": { "stringValue": "no key" } }, { "key": "no value" }] });
+        assert_eq!(attributes(&record, "attributes"), json!({ "no value": null }).as_object().unwrap().clone());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::storage::Payload;
+use serde_json::Value;
+
+/// Key marking a payload that uses the typed dbug protocol
+const ENVELOPE_KEY: &str = "$dbug";
+
+/// Payload types with a specialized renderer
+const KINDS: [&str; 6] = ["log", "table", "exception", "timer", "counter", "span"];
+
+/// Unpacks a typed envelope such as `{"$dbug": {"type": "log", "level": "warn"}, "data": "..."}`
+///
+/// The envelope's `type` becomes the payload kind, its `id` the client ID and its other options
+/// become metadata. An envelope may carry only an `id` to update plain JSON in place, or only the
+/// `file` and `line` it was sent from, as `dbug!` does.
+/// Anything that is not a recognized envelope is stored as plain JSON.
+pub fn payload(value: Value) -> Payload {
+    let envelope = value.get(ENVELOPE_KEY);
+    let kind = envelope.and_then(|envelope| envelope.get("type"));
+    let recognized = match kind {
+        Some(kind) => kind.as_str().is_some_and(|kind| KINDS.contains(&kind)),
+        None => envelope.is_some_and(|envelope| envelope.get("id").is_some() || envelope.get("file").is_some()),
+    };
+    if !recognized {
+        return Payload::new(value);
+    }
+
+    let Value::Object(mut object) = value else {
+        return Payload::new(value);
+    };
+    let options = object.remove(ENVELOPE_KEY).and_then(|envelope| match envelope {
+        Value::Object(options) => Some(options),
+        _ => None,
+    });
+    let data = object.remove("data").unwrap_or(Value::Null);
+
+    let mut payload = options
+        .into_iter()
+        .flatten()
+        .fold(Payload::new(data), |payload, (key, value)| match (key.as_str(), value) {
+            ("id", Value::String(id)) => payload.with_client_id(id),
+            ("id", Value::Number(id)) => payload.with_client_id(id.to_string()),
+            ("type", value) => payload.with_meta("kind", value),
+            (key, value) => payload.with_meta(key, value),
+        });
+
+    // A call site is shown as one badge, e.g. "source: src/main.rs:42"
+    if let (Some(Value::String(file)), Some(line)) = (payload.meta.get("file"), payload.meta.get("line")) {
+        let source = format!("{file}:{line}");
+        payload.meta.remove("file");
+        payload.meta.remove("line");
+        payload = payload.with_meta("source", source);
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn typed_envelope_sets_kind_client_id_and_options() {
+        let payload = payload(json!({
+            "$dbug": { "type": "log", "id": "job-7", "level": "warn" },
+            "data": "disk almost full",
+        }));
+        assert_eq!(payload.value, json!("disk almost full"));
+        assert_eq!(payload.client_id.as_deref(), Some("job-7"));
+        assert_eq!(payload.meta["kind"], "log");
+        assert_eq!(payload.meta["level"], "warn");
+    }
+
+    #[test]
+    fn id_only_envelope_updates_plain_json() {
+        let payload = payload(json!({ "$dbug": { "id": 42 }, "data": { "progress": 0.5 } }));
+        assert_eq!(payload.value, json!({ "progress": 0.5 }));
+        assert_eq!(payload.client_id.as_deref(), Some("42"));
+        assert_eq!(payload.meta.get("kind"), None);
+    }
+
+    #[test]
+    fn call_site_becomes_one_source_badge() {
+        let payload = payload(json!({
+            "$dbug": { "file": "src/main.rs", "line": 42, "module": "app" },
+            "data": [1, 2],
+        }));
+        assert_eq!(payload.value, json!([1, 2]));
+        assert_eq!(payload.meta["source"], "src/main.rs:42");
+        assert_eq!(payload.meta["module"], "app");
+        assert_eq!(payload.meta.get("file"), None);
+        assert_eq!(payload.meta.get("line"), None);
+    }
+
+    #[test]
+    fn envelope_without_data_carries_null() {
+        let payload = payload(json!({ "$dbug": { "type": "counter", "name": "hits" } }));
+        assert_eq!(payload.value, Value::Null);
+        assert_eq!(payload.meta["kind"], "counter");
+    }
+
+    #[test]
+    fn unrecognized_envelopes_are_stored_as_plain_json() {
+        for value in [
+            json!({ "message": "no envelope" }),
+            json!({ "$dbug": { "type": "unknown" }, "data": 1 }),
+            json!({ "$dbug": { "type": 7 }, "data": 1 }),
+            json!({ "$dbug": { "level": "warn" }, "data": 1 }),
+            json!({ "$dbug": "log", "data": 1 }),
+            json!({ "$dbug": null }),
+            json!(["$dbug"]),
+            json!("$dbug"),
+        ] {
+            let payload = payload(value.clone());
+            assert_eq!(payload.value, value);
+            assert!(payload.meta.is_empty(), "{value}");
+            assert_eq!(payload.client_id, None, "{value}");
+        }
+    }
+
+    #[test]
+    fn ids_of_other_types_are_kept_as_metadata() {
+        let payload = payload(json!({ "$dbug": { "type": "timer", "id": ["not", "an", "id"] }, "data": 5 }));
+        assert_eq!(payload.client_id, None);
+        assert_eq!(payload.meta["id"], json!(["not", "an", "id"]));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::server::ServerMessage;
+use crate::storage::Payload;
+use serde_json::{json, Map, Value};
+
+/// Converts a Ray exception into the typed dbug exception shape, most recent call first
+fn exception(content: &Value) -> Value {
+    let frames = content
+        .get("frames")
+        .and_then(Value::as_array)
+        .map(|frames| {
+            frames
+                .iter()
+                .map(|frame| {
+                    let function = match (frame.get("class").and_then(Value::as_str), frame.get("method").and_then(Value::as_str)) {
+                        (Some(class), Some(method)) if !class.is_empty() => format!("{class}::{method}"),
+                        (_, Some(method)) => method.to_string(),
+                        _ => "<unknown>".to_string(),
+                    };
+                    json!({
+                        "function": function,
+                        "file": frame.get("file_name"),
+                        "line": frame.get("line_number"),
+                        "in_app": !frame.get("vendor_frame").and_then(Value::as_bool).unwrap_or(false),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    json!({
+        "type": content.get("class"),
+        "message": content.get("message"),
+        "frames": frames,
+    })
+}
+
+/// Ray tables are key/value maps; lay them out as rows for the table view
+fn table_rows(values: &Value) -> Value {
+    match values {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect(),
+        other => other.clone(),
+    }
+}
+
+/// Builds a dbug payload from a Ray content payload, or `None` for a type without content
+fn content_payload(kind: &str, content: &Value) -> Option<Payload> {
+    let label = content.get("label").cloned().unwrap_or(Value::Null);
+    let payload = match kind {
+        "log" => {
+            let values = content.get("values").cloned().unwrap_or(Value::Null);
+            // A single logged value is shown on its own rather than as a one-element array
+            match values {
+                Value::Array(mut values) if values.len() == 1 => Payload::new(values.remove(0)),
+                values => Payload::new(values),
+            }
+        }
+        "custom" | "text" | "html" => Payload::new(content.get("content").cloned().unwrap_or(Value::Null)),
+        "json_string" => {
+            let json = content.get("value").and_then(Value::as_str).unwrap_or_default();
+            Payload::new(serde_json::from_str(json).unwrap_or_else(|_| json.into()))
+        }
+        "table" => Payload::new(table_rows(content.get("values").unwrap_or(&Value::Null))).with_meta("kind", "table"),
+        "exception" => Payload::new(exception(content)).with_meta("kind", "exception"),
+        "color" | "label" | "remove" | "size" | "hide" | "show_app" | "hide_app" | "new_screen" | "clear_all" => {
+            return None
+        }
+        other => Payload::new(content.clone()).with_meta("ray_type", other),
+    };
+    Some(payload.with_meta("label", label))
+}
+
+/// Formats a Ray origin as "file:line"
+fn origin(payload: &Value) -> Value {
+    let origin = payload.get("origin");
+    let file = origin.and_then(|origin| origin.get("file")).and_then(Value::as_str);
+    let line = origin.and_then(|origin| origin.get("line_number"));
+    match (file, line) {
+        (Some(file), Some(line)) => format!("{file}:{line}").into(),
+        (Some(file), None) => file.into(),
+        _ => Value::Null,
+    }
+}
+
+/// Translates a Spatie Ray request into server messages, or `None` when `body` is not one
+///
+/// Ray requests carry a `uuid` and a list of typed `payloads`. Content payloads create
+/// entries tagged with the uuid; "color", "label" and "remove" payloads sent later with the
+/// same uuid update or delete those entries.
+pub(crate) fn messages(body: &Value) -> Option<Vec<ServerMessage>> {
+    let uuid = body.get("uuid")?.as_str()?;
+    let payloads = body.get("payloads")?.as_array()?;
+
+    let mut messages = Vec::new();
+    for payload in payloads {
+        let kind = payload.get("type").and_then(Value::as_str).unwrap_or_default();
+        let content = payload.get("content").unwrap_or(&Value::Null);
+
+        let mut meta = Map::new();
+        match kind {
+            "color" | "label" => {
+                if let Some(value) = content.get(kind) {
+                    meta.insert(kind.to_string(), value.clone());
+                }
+                messages.push(ServerMessage::MetaUpdated { client_id: uuid.to_string(), meta });
+            }
+            "remove" => messages.push(ServerMessage::PayloadRemoved { client_id: uuid.to_string() }),
+            _ => {
+                if let Some(entry) = content_payload(kind, content) {
+                    let entry = entry.with_meta("origin", origin(payload)).with_client_id(uuid);
+                    messages.push(ServerMessage::PayloadReceived(entry));
+                }
+            }
+        }
+    }
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "6f0c1b8e-5a0c-4a3a-9a55-3f2b8d5c1e21";
+
+    // What `ray('hello')->green()->label('greeting')` sends, one request per call
+    fn request(payload: Value) -> Value {
+        json!({
+            "uuid": UUID,
+            "payloads": [payload],
+            "meta": { "php_version": "8.3.4", "ray_package_version": "1.41.2" },
+        })
+    }
+
+    fn received(messages: Vec<ServerMessage>) -> Payload {
+        match <[ServerMessage; 1]>::try_from(messages) {
+            Ok([ServerMessage::PayloadReceived(payload)]) => payload,
+            Ok([other]) => panic!("expected a payload, got {other:?}"),
+            Err(messages) => panic!("expected one message, got {messages:?}"),
+        }
+    }
+
+    #[test]
+    fn log_payload_keeps_the_uuid_and_origin() {
+        let messages = messages(&request(json!({
+            "type": "log",
+            "content": { "values": ["hello"], "meta": [{ "clipboard_data": "hello" }] },
+            "origin": { "function_name": "ray", "file": "/app/routes/web.php", "line_number": 12, "hostname": "mbp" },
+        })))
+        .unwrap();
+
+        let payload = received(messages);
+        assert_eq!(payload.value, json!("hello"));
+        assert_eq!(payload.client_id.as_deref(), Some(UUID));
+        assert_eq!(payload.meta["origin"], "/app/routes/web.php:12");
+    }
+
+    #[test]
+    fn exception_payload_becomes_a_typed_exception() {
+        let messages = messages(&request(json!({
+            "type": "exception",
+            "content": {
+                "class": "RuntimeException",
+                "message": "boom",
+                "frames": [
+                    { "class": "App\\Jobs\\Import", "method": "handle", "file_name": "app/Jobs/Import.php", "line_number": 30, "vendor_frame": false },
+                    { "class": "", "method": "{closure}", "file_name": "vendor/laravel/framework/src/Bus.php", "line_number": 9, "vendor_frame": true },
+                ],
+            },
+        })))
+        .unwrap();
+
+        let payload = received(messages);
+        assert_eq!(payload.meta["kind"], "exception");
+        assert_eq!(payload.value["type"], "RuntimeException");
+        assert_eq!(payload.value["frames"][0]["function"], "App\\Jobs\\Import::handle");
+        assert_eq!(payload.value["frames"][0]["in_app"], true);
+        assert_eq!(payload.value["frames"][1]["function"], "{closure}");
+        assert_eq!(payload.value["frames"][1]["in_app"], false);
+    }
+
+    #[test]
+    fn color_and_label_update_the_entries_sent_with_the_uuid() {
+        for (kind, value) in [("color", "green"), ("label", "greeting")] {
+            let messages = messages(&request(json!({ "type": kind, "content": { kind: value } }))).unwrap();
+            let [ServerMessage::MetaUpdated { client_id, meta }] = messages.as_slice() else {
+                panic!("expected a metadata update, got {messages:?}");
+            };
+            assert_eq!(client_id, UUID);
+            assert_eq!(meta[kind], value);
+        }
+    }
+
+    #[test]
+    fn remove_deletes_the_entries_sent_with_the_uuid() {
+        let messages = messages(&request(json!({ "type": "remove", "content": [] }))).unwrap();
+        assert!(matches!(
+            messages.as_slice(),
+            [ServerMessage::PayloadRemoved { client_id }] if client_id == UUID
+        ));
+    }
+
+    #[test]
+    fn screen_and_app_commands_store_nothing() {
+        for kind in ["size", "hide", "show_app", "hide_app", "new_screen", "clear_all"] {
+            let messages = messages(&request(json!({ "type": kind, "content": {} }))).unwrap();
+            assert!(messages.is_empty(), "{kind}");
+        }
+    }
+
+    #[test]
+    fn non_ray_bodies_are_not_taken_for_ray_requests() {
+        for body in [
+            json!({ "message": "plain JSON" }),
+            json!({ "uuid": 42, "payloads": [] }),
+            json!({ "uuid": UUID, "payloads": "not a list" }),
+            json!([UUID]),
+        ] {
+            assert!(messages(&body).is_none(), "{body}");
+        }
+    }
+
+    #[test]
+    fn malformed_payloads_do_not_panic() {
+        let body = json!({
+            "uuid": UUID,
+            "payloads": [
+                null,
+                { "type": "color" },
+                { "type": "json_string", "content": { "value": "{not json" } },
+                { "type": "exception", "content": { "frames": "not a list" } },
+            ],
+        });
+        let messages = messages(&body).unwrap();
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(&messages[1], ServerMessage::MetaUpdated { meta, .. } if meta.is_empty()));
+        let ServerMessage::PayloadReceived(unparsed) = &messages[2] else {
+            panic!("expected the unparsed string, got {:?}", messages[2]);
+        };
+        assert_eq!(unparsed.value, json!("{not json"));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::storage::Payload;
+use serde_json::Value;
+
+/// Wraps a Sentry event as an "exception" payload
+pub(crate) fn event(event: Value) -> Payload {
+    let level = event.get("level").cloned().unwrap_or(Value::Null);
+    Payload::new(event)
+        .with_meta("kind", "exception")
+        .with_meta("level", level)
+}
+
+/// Reads one envelope line, returning it and the remaining input
+fn next_line(input: &[u8]) -> (&[u8], &[u8]) {
+    match input.iter().position(|byte| *byte == b'\n') {
+        Some(end) => (&input[..end], &input[end + 1..]),
+        None => (input, &[]),
+    }
+}
+
+/// Extracts the events from a Sentry envelope, ignoring other item types
+///
+/// An envelope is a JSON header line followed by items, each an item header line and
+/// a payload that is either `length` bytes long or runs to the end of the line.
+pub(crate) fn envelope(body: &[u8]) -> Result<Vec<Payload>, String> {
+    let (_header, mut rest) = next_line(body);
+    let mut payloads = Vec::new();
+
+    while !rest.is_empty() {
+        let (item_header, after_header) = next_line(rest);
+        if item_header.iter().all(u8::is_ascii_whitespace) {
+            rest = after_header;
+            continue;
+        }
+        let item_header: Value = serde_json::from_slice(item_header)
+            .map_err(|e| format!("invalid envelope item header: {e}"))?;
+
+        let (item, remaining) = match item_header.get("length").and_then(Value::as_u64) {
+            Some(length) => {
+                let length = usize::try_from(length).unwrap_or(usize::MAX);
+                if length > after_header.len() {
+                    return Err("envelope item is shorter than its length".to_string());
+                }
+                let (item, remaining) = after_header.split_at(length);
+                // Skip the newline that may follow a sized payload
+                (item, remaining.strip_prefix(b"\n").unwrap_or(remaining))
+            }
+            None => next_line(after_header),
+        };
+        rest = remaining;
+
+        if item_header.get("type").and_then(Value::as_str) == Some("event") {
+            let value = serde_json::from_slice(item).map_err(|e| format!("invalid event: {e}"))?;
+            payloads.push(event(value));
+        }
+    }
+
+    Ok(payloads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // The full example from Sentry's envelope documentation: a sized attachment and a sized event
+    const DOCUMENTED: &[u8] = b"{\"event_id\":\"9ec79c33ec9942ab8353589fcb2e04dc\",\"dsn\":\"https://e12d836b15bb49d7bbf99e64295d995b:@sentry.io/42\"}\n\
+{\"type\":\"attachment\",\"length\":10,\"content_type\":\"text/plain\",\"filename\":\"hello.txt\"}\n\
+\xef\xbb\xbfHello\r\n\n\
+{\"type\":\"event\",\"length\":41,\"content_type\":\"application/json\",\"filename\":\"application.log\"}\n\
+{\"message\":\"hello world\",\"level\":\"error\"}\n";
+
+    #[test]
+    fn envelope_extracts_sized_events_and_skips_attachments() {
+        let payloads = envelope(DOCUMENTED).unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].value, json!({ "message": "hello world", "level": "error" }));
+        assert_eq!(payloads[0].meta["kind"], "exception");
+        assert_eq!(payloads[0].meta["level"], "error");
+    }
+
+    #[test]
+    fn envelope_reads_items_that_run_to_the_end_of_the_line() {
+        let body = b"{\"event_id\":\"a\"}\n\
+{\"type\":\"session\"}\n{\"sid\":\"b\",\"status\":\"ok\"}\n\
+\n\
+{\"type\":\"event\"}\n{\"exception\":{\"values\":[{\"type\":\"ValueError\",\"value\":\"bad\"}]}}";
+        let payloads = envelope(body).unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].value["exception"]["values"][0]["type"], "ValueError");
+        // An event without a level has none to show
+        assert_eq!(payloads[0].meta.get("level"), None);
+    }
+
+    #[test]
+    fn envelope_without_items_is_empty() {
+        assert!(envelope(b"").unwrap().is_empty());
+        assert!(envelope(b"{\"event_id\":\"a\"}\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_envelopes_are_rejected() {
+        let cases: [&[u8]; 4] = [
+            b"{}\nnot a header\n{}\n",
+            b"{}\n{\"type\":\"event\",\"length\":100}\n{\"message\":\"short\"}\n",
+            b"{}\n{\"type\":\"event\"}\n{\"message\":\n",
+            b"{}\n{\"type\":\"event\",\"length\":18446744073709551615}\n{}\n",
+        ];
+        for body in cases {
+            assert!(envelope(body).is_err(), "{}", String::from_utf8_lossy(body));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Keeps a single window, terminal UI or headless server running at a time
+//!
+//! The running instance holds an exclusive lock on `~/.dbug_desktop/instance.lock` and listens on
+//! `~/.dbug_desktop/instance.sock`, or on Windows on a loopback port recorded in
+//! `~/.dbug_desktop/instance.json`. A later launch that finds the lock taken connects, forwards its
+//! command line and exits instead of competing for the server port and `data.json`. Forwarded
+//! command lines must carry the token in `instance.json`, which only the current user can read.
+
+use dbug::protocol;
+use dbug::server::ServerMessage;
+use dbug::storage::{Payload, Storage};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, BufRead, BufReader, Read, Write};
+#[cfg(not(unix))]
+use std::net::{Ipv4Addr, SocketAddr, TcpListener as Listener, TcpStream as Connection};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Connection};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// How long a launch waits for the running instance to accept and answer
+#[cfg(not(unix))]
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Longer requests are not command lines
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+// How long a launch keeps trying to reach an instance that holds the lock but may still be starting
+const FORWARD_ATTEMPTS: u32 = 20;
+const FORWARD_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Options whose value is the next argument, so it is not taken for a file
+const OPTIONS_WITH_VALUES: [&str; 1] = ["--format"];
+
+// Forwarded command lines, until a front-end takes the stream
+static FORWARDED: Mutex<Option<UnboundedReceiver<Forwarded>>> = Mutex::new(None);
+
+/// A command line passed on to the running instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Forwarded {
+    pub args: Vec<String>,
+    /// Working directory of the launch, which relative paths in `args` are resolved against
+    pub cwd: PathBuf,
+}
+
+impl Forwarded {
+    fn current(args: &[String]) -> Self {
+        Self {
+            args: args.to_vec(),
+            cwd: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// Files named on the command line: every argument except options and their values
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if OPTIONS_WITH_VALUES.contains(&arg.as_str()) {
+                args.next();
+            } else if !arg.starts_with('-') {
+                files.push(self.cwd.join(arg));
+            }
+        }
+        files
+    }
+
+    /// Reads the files named on the command line as payloads to import
+    ///
+    /// A file written by `dbug export` restores its payloads; any other JSON becomes one payload.
+    pub fn messages(&self) -> Vec<ServerMessage> {
+        self.files()
+            .into_iter()
+            .filter_map(|path| match import(&path) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    eprintln!("WARN: Failed to import {}: {e}", path.display());
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn import(path: &Path) -> io::Result<ServerMessage> {
+    let value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(match serde_json::from_value::<Vec<Payload>>(value.clone()) {
+        Ok(payloads) => ServerMessage::PayloadsReceived(payloads),
+        Err(_) => ServerMessage::PayloadReceived(protocol::payload(value)),
+    })
+}
+
+/// Where the running instance can be reached, readable only by the user who started it
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceFile {
+    pid: u32,
+    /// Presented with every forwarded command line, so only those who can read this file can forward
+    token: String,
+    /// The loopback port the instance listens on where there are no Unix sockets
+    #[cfg(not(unix))]
+    port: u16,
+}
+
+/// What a launch sends to the running instance
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    token: String,
+    forwarded: Forwarded,
+}
+
+fn instance_path() -> PathBuf {
+    Storage::config_path().with_file_name("instance.json")
+}
+
+fn lock_path() -> PathBuf {
+    Storage::config_path().with_file_name("instance.lock")
+}
+
+#[cfg(unix)]
+fn socket_path() -> PathBuf {
+    Storage::config_path().with_file_name("instance.sock")
+}
+
+/// A secret known only to this process and to those who can read `instance.json`
+fn new_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    // Each `RandomState` is keyed from the operating system's random source
+    let part = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", part(), part())
+}
+
+/// Marks this process as the running instance until dropped
+pub(crate) struct Instance {
+    path: PathBuf,
+    // Held for the life of the instance; the OS releases it even if the process does not exit cleanly
+    _lock: File,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        // Nothing else writes these while the lock is held, so they are still ours
+        let _ = std::fs::remove_file(&self.path);
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(socket_path());
+    }
+}
+
+/// Becomes the running instance, or forwards `args` to the one already running and returns `None`
+///
+/// The arguments of the instance that starts are forwarded to itself, so files named when launching
+/// are imported the same way either way.
+pub(crate) fn acquire(args: &[String]) -> io::Result<Option<Instance>> {
+    let path = instance_path();
+    let forwarded = Forwarded::current(args);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        // Only the user running dbug may reach its socket, read its token or read its payloads
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(lock_path())?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let pid = forward_to_running(&path, &forwarded)?;
+            eprintln!("INFO: dbug is already running (pid {pid}); forwarded to it");
+            return Ok(None);
+        }
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+
+    // Anything already there was left behind by an instance that did not exit cleanly
+    let token = new_token();
+    let (listener, file) = listen(token.clone())?;
+    write_instance_file(&path, &file)?;
+
+    let (sender, receiver) = mpsc::unbounded();
+    if let Ok(mut slot) = FORWARDED.lock() {
+        *slot = Some(receiver);
+    }
+    let _ = sender.unbounded_send(forwarded);
+    std::thread::Builder::new()
+        .name("dbug-instance".to_string())
+        .spawn(move || accept(&listener, &token, &sender))?;
+
+    Ok(Some(Instance { path, _lock: lock }))
+}
+
+/// Listens on a socket only this user can connect to
+#[cfg(unix)]
+fn listen(token: String) -> io::Result<(Listener, InstanceFile)> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let path = socket_path();
+    match std::fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(&path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = Listener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    let file = InstanceFile {
+        pid: std::process::id(),
+        token,
+    };
+    Ok((listener, file))
+}
+
+/// Listens on a loopback port, which launches find in `instance.json`
+#[cfg(not(unix))]
+fn listen(token: String) -> io::Result<(Listener, InstanceFile)> {
+    let listener = Listener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let file = InstanceFile {
+        pid: std::process::id(),
+        token,
+        port: listener.local_addr()?.port(),
+    };
+    Ok((listener, file))
+}
+
+/// Writes `instance.json` so that only the current user can read it
+fn write_instance_file(path: &Path, file: &InstanceFile) -> io::Result<()> {
+    // The permissions are only set when the file is created
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(serde_json::to_string(file)?.as_bytes())
+}
+
+#[cfg(unix)]
+fn connect(_running: &InstanceFile) -> io::Result<Connection> {
+    Connection::connect(socket_path())
+}
+
+#[cfg(not(unix))]
+fn connect(running: &InstanceFile) -> io::Result<Connection> {
+    Connection::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, running.port)), CONNECT_TIMEOUT)
+}
+
+/// Forwards a command line to the instance holding the lock and returns its pid
+///
+/// The instance may have taken the lock without having written `instance.json` yet, or the file may
+/// still be one an earlier instance left behind, so this retries for a moment before giving up.
+fn forward_to_running(path: &Path, forwarded: &Forwarded) -> io::Result<u32> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no instance file");
+    for _ in 0..FORWARD_ATTEMPTS {
+        let running = std::fs::read_to_string(path)
+            .and_then(|contents| serde_json::from_str::<InstanceFile>(&contents).map_err(io::Error::from));
+        match running.and_then(|running| forward(&running, forwarded).map(|()| running.pid)) {
+            Ok(pid) => return Ok(pid),
+            Err(e) => last_error = e,
+        }
+        std::thread::sleep(FORWARD_RETRY_DELAY);
+    }
+    Err(io::Error::new(
+        last_error.kind(),
+        format!("dbug is already running but could not be reached: {last_error}"),
+    ))
+}
+
+/// Sends a command line to the running instance and waits for it to confirm
+fn forward(running: &InstanceFile, forwarded: &Forwarded) -> io::Result<()> {
+    let mut stream = connect(running)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let request = Request {
+        token: running.token.clone(),
+        forwarded: forwarded.clone(),
+    };
+    writeln!(stream, "{}", serde_json::to_string(&request)?)?;
+
+    // An instance that did not write this token, or whatever else may listen there, will not answer
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "no reply from a running instance"))
+    }
+}
+
+fn accept(listener: &Listener, token: &str, sender: &UnboundedSender<Forwarded>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+        if !answer(&stream, token, sender) {
+            break;
+        }
+    }
+}
+
+/// Reads one request and passes on its command line if it carries `token`
+///
+/// Returns whether the instance still takes forwarded command lines.
+fn answer<S>(mut stream: S, token: &str, sender: &UnboundedSender<Forwarded>) -> bool
+where
+    S: Read + Write,
+{
+    let mut line = String::new();
+    if let Err(e) = BufReader::new((&mut stream).take(MAX_REQUEST_BYTES)).read_line(&mut line) {
+        eprintln!("WARN: Failed to read forwarded arguments: {e}");
+        return true;
+    }
+    match serde_json::from_str::<Request>(&line) {
+        Ok(request) if request.token == token => {
+            let _ = stream.write_all(b"ok\n");
+            sender.unbounded_send(request.forwarded).is_ok()
+        }
+        Ok(_) => {
+            eprintln!("WARN: Ignoring forwarded arguments without this instance's token");
+            true
+        }
+        Err(e) => {
+            eprintln!("WARN: Ignoring unreadable forwarded arguments: {e}");
+            true
+        }
+    }
+}
+
+/// Command lines forwarded to this instance, including its own; the stream can be taken once
+pub(crate) fn forwarded() -> impl Stream<Item = Forwarded> {
+    let receiver = FORWARDED.lock().ok().and_then(|mut slot| slot.take());
+    futures::stream::iter(receiver).flatten()
+}
+
+/// Payloads imported from files named on forwarded command lines
+pub(crate) fn imports() -> impl Stream<Item = ServerMessage> {
+    forwarded().flat_map(|forwarded| futures::stream::iter(forwarded.messages()))
+}
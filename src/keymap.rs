@@ -0,0 +1,146 @@
+use iced::keyboard::{key, Key, Modifiers};
+
+/// Something the user can do with the keyboard inside the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyAction {
+    FocusNext,
+    FocusPrevious,
+    CloseModal,
+    OpenSettings,
+    SelectPrevious,
+    SelectNext,
+    SelectFirst,
+    SelectLast,
+    ToggleExpanded,
+    Delete,
+    Copy,
+    Search,
+    TogglePin,
+}
+
+impl KeyAction {
+    /// Whether the action only makes sense for the payload list, not the settings modal
+    pub(crate) fn is_list_action(self) -> bool {
+        !matches!(self, Self::FocusNext | Self::FocusPrevious | Self::CloseModal | Self::OpenSettings)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum KeyPattern {
+    Named(key::Named),
+    Character(&'static str),
+}
+
+/// A key, the modifiers it needs and the action it triggers
+#[derive(Debug)]
+pub(crate) struct KeyBinding {
+    key: KeyPattern,
+    /// Only checked for named keys; characters already carry their case, e.g. `g` and `G`
+    shift: bool,
+    command: bool,
+    /// Also handled while a text input has focus
+    while_typing: bool,
+    action: KeyAction,
+    label: &'static str,
+    description: &'static str,
+}
+
+const fn bind(key: KeyPattern, action: KeyAction, label: &'static str, description: &'static str) -> KeyBinding {
+    KeyBinding {
+        key,
+        shift: false,
+        command: false,
+        while_typing: false,
+        action,
+        label,
+        description,
+    }
+}
+
+const fn character(character: &'static str) -> KeyPattern {
+    KeyPattern::Character(character)
+}
+
+const fn named(named: key::Named) -> KeyPattern {
+    KeyPattern::Named(named)
+}
+
+/// Every in-window shortcut; bindings for the same action are listed next to each other
+pub(crate) const KEYMAP: &[KeyBinding] = &[
+    KeyBinding { while_typing: true, ..bind(named(key::Named::Tab), KeyAction::FocusNext, "Tab", "Focus the next field") },
+    KeyBinding {
+        shift: true,
+        while_typing: true,
+        ..bind(named(key::Named::Tab), KeyAction::FocusPrevious, "Shift+Tab", "Focus the previous field")
+    },
+    KeyBinding { while_typing: true, ..bind(named(key::Named::Escape), KeyAction::CloseModal, "Esc", "Close Settings") },
+    KeyBinding { command: true, while_typing: true, ..bind(character(","), KeyAction::OpenSettings, "Cmd+,", "Open Settings") },
+    bind(named(key::Named::ArrowUp), KeyAction::SelectPrevious, "↑", "Select the previous payload"),
+    bind(character("k"), KeyAction::SelectPrevious, "k", "Select the previous payload"),
+    bind(named(key::Named::ArrowDown), KeyAction::SelectNext, "↓", "Select the next payload"),
+    bind(character("j"), KeyAction::SelectNext, "j", "Select the next payload"),
+    bind(character("g"), KeyAction::SelectFirst, "g", "Jump to the newest payload"),
+    bind(character("G"), KeyAction::SelectLast, "G", "Jump to the oldest payload"),
+    bind(named(key::Named::Enter), KeyAction::ToggleExpanded, "Enter", "Expand or collapse the selected payload"),
+    bind(character("d"), KeyAction::Delete, "d", "Delete the selected payload"),
+    bind(named(key::Named::Delete), KeyAction::Delete, "Delete", "Delete the selected payload"),
+    bind(character("c"), KeyAction::Copy, "c", "Copy the selected payload's JSON"),
+    bind(character("/"), KeyAction::Search, "/", "Search the selected payload"),
+    bind(character("p"), KeyAction::TogglePin, "p", "Pin or unpin the selected payload"),
+];
+
+impl KeyBinding {
+    fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        if modifiers.command() != self.command {
+            return false;
+        }
+        match (self.key, key) {
+            (KeyPattern::Named(expected), Key::Named(pressed)) => {
+                expected == *pressed && modifiers.shift() == self.shift
+            }
+            (KeyPattern::Character(expected), Key::Character(pressed)) => expected == pressed.as_str(),
+            _ => false,
+        }
+    }
+}
+
+/// The action bound to a key press, if any
+pub(crate) fn action_for(key: &Key, modifiers: Modifiers) -> Option<KeyAction> {
+    KEYMAP
+        .iter()
+        .find(|binding| binding.matches(key, modifiers))
+        .map(|binding| binding.action)
+}
+
+/// What a key press does inside the window, if it is bound
+pub(crate) fn description_for(key: &Key, modifiers: Modifiers) -> Option<&'static str> {
+    KEYMAP
+        .iter()
+        .find(|binding| binding.matches(key, modifiers))
+        .map(|binding| binding.description)
+}
+
+/// Whether a key press should reach the keymap even though a text input captured it
+pub(crate) fn works_while_typing(key: &Key, modifiers: Modifiers) -> bool {
+    KEYMAP
+        .iter()
+        .any(|binding| binding.while_typing && binding.matches(key, modifiers))
+}
+
+/// Shortcut labels and descriptions for display, with an action's bindings joined, e.g. "↑ / k"
+pub(crate) fn shortcuts() -> Vec<(String, &'static str)> {
+    let mut shortcuts: Vec<(KeyAction, String, &'static str)> = Vec::new();
+    for binding in KEYMAP {
+        match shortcuts.last_mut() {
+            Some((action, label, _)) if *action == binding.action => {
+                label.push_str(" / ");
+                label.push_str(binding.label);
+            }
+            _ => shortcuts.push((binding.action, binding.label.to_string(), binding.description)),
+        }
+    }
+    shortcuts
+        .into_iter()
+        .map(|(_, label, description)| (label, description))
+        .collect()
+}
@@ -0,0 +1,31 @@
+//! The core of dbug: a local server that receives JSON payloads and the store that keeps them
+//!
+//! The desktop window, the terminal UI and the command line are all built on this crate, and it can
+//! be embedded elsewhere, e.g. to run a dbug server inside a test harness:
+//!
+//! ```no_run
+//! use dbug::{server, Settings, Storage};
+//! use futures::StreamExt;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut settings = Settings::default();
+//! settings.set_server_port(53999);
+//! let storage = Storage::in_dir(std::env::temp_dir().join("dbug-test"))?;
+//!
+//! let mut messages = Box::pin(server::listen_with(settings));
+//! while let Some(message) = messages.next().await {
+//!     message.store(&storage)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod ingest;
+pub mod server;
+pub mod settings;
+pub mod storage;
+
+pub use ingest::protocol;
+pub use server::ServerMessage;
+pub use settings::Settings;
+pub use storage::{Payload, Revision, Storage};
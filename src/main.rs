@@ -1,11 +1,57 @@
 mod app;
+mod cli;
 mod components;
 mod gui;
-mod server;
-mod settings;
-mod storage;
+mod headless;
+mod hotkeys;
+mod instance;
+mod keymap;
+mod tui;
+
+use headless::OutputFormat;
+
+/// Value following `name` on the command line, as in `--format json` or `--format=json`
+fn option_value(args: &[String], name: &str) -> Option<String> {
+    args.iter().enumerate().find_map(|(idx, arg)| {
+        if arg == name {
+            args.get(idx + 1).cloned()
+        } else {
+            arg.strip_prefix(name)?.strip_prefix('=').map(str::to_string)
+        }
+    })
+}
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Subcommands talk to a running instance or read the stored payloads, without opening a window
+    if let Some(command) = args.first().filter(|arg| cli::COMMANDS.contains(&arg.as_str())) {
+        return cli::run(command, &args[1..]);
+    }
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return cli::run("help", &[]);
+    }
+
+    // A second launch hands its arguments to the running instance rather than competing for its port
+    let Some(_instance) = instance::acquire(&args)? else {
+        return Ok(());
+    };
+
+    // Serve and store payloads without opening a window, e.g. on CI
+    if args.iter().any(|arg| arg == "--headless") {
+        let format = match option_value(&args, "--format") {
+            Some(name) => OutputFormat::parse(&name)
+                .ok_or_else(|| format!("Unknown format {name}, expected json, pretty or summary"))?,
+            None => OutputFormat::Json,
+        };
+        return headless::run(format);
+    }
+
+    // Show payloads in the terminal, e.g. over SSH where no window can be opened
+    if args.iter().any(|arg| arg == "--tui") {
+        return tui::run();
+    }
+
     gui::gui()?;
     Ok(())
 }
@@ -1,13 +1,66 @@
-use iced::futures::Stream;
-use iced::stream;
-use serde_json::Value;
+use futures::{SinkExt, Stream, StreamExt};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::io::{self, Read};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use warp::http::{header, HeaderMap, StatusCode};
+use warp::hyper::body::{Buf, Bytes};
+use warp::path::FullPath;
 use warp::{hyper::Method, Filter};
-use iced::futures::SinkExt;
-use crate::settings::Settings;
+use crate::ingest::{otlp, protocol, ray, sentry};
+use crate::settings::{Settings, UpstreamRoute, DEFAULT_SERVER_PORT};
+use crate::storage::{self, Payload, Storage};
 
 #[derive(Debug, Clone)]
 pub enum ServerMessage {
-    PayloadReceived(Value),
+    PayloadReceived(Payload),
+    /// A request relayed to an upstream, paired with the upstream's response
+    ExchangeRecorded { request: Value, response: Value },
+    /// Many payloads from a single request, stored and rendered together
+    PayloadsReceived(Vec<Payload>),
+    /// Metadata to merge into the payloads sent with `client_id`
+    MetaUpdated { client_id: String, meta: Map<String, Value> },
+    /// The client removed the payloads it sent with `client_id`
+    PayloadRemoved { client_id: String },
+    /// A request was refused, with the reason sent back to the client
+    RequestRejected(String),
+    /// A client asked for every payload to be cleared
+    ClearRequested,
+}
+
+impl ServerMessage {
+    /// New payloads carried by the message, before storing assigns their final ids
+    pub fn payloads(&self) -> Vec<Payload> {
+        match self {
+            Self::PayloadReceived(payload) => vec![payload.clone()],
+            Self::PayloadsReceived(payloads) => payloads.clone(),
+            Self::ExchangeRecorded { request, response } => {
+                let mut exchange = Payload::new(request.clone());
+                exchange.response = Some(response.clone());
+                vec![exchange]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Applies the message to `storage`, returning the new payloads as they were stored
+    ///
+    /// Rejections carry nothing to store and are left to the caller.
+    pub fn store(self, storage: &Storage) -> io::Result<Vec<Payload>> {
+        match self {
+            Self::PayloadReceived(payload) => storage.add_payloads(vec![payload]),
+            Self::PayloadsReceived(payloads) => storage.add_payloads(payloads),
+            Self::ExchangeRecorded { request, response } => {
+                Ok(storage.add_exchange(&request, &response)?.into_iter().collect())
+            }
+            Self::MetaUpdated { client_id, meta } => storage
+                .update_by_client_id(&client_id, |payload| payload.meta.extend(meta.clone()))
+                .map(|_| Vec::new()),
+            Self::PayloadRemoved { client_id } => storage.delete_by_client_id(&client_id).map(|_| Vec::new()),
+            Self::ClearRequested => storage.delete_all().map(|()| Vec::new()),
+            Self::RequestRejected(_) => Ok(Vec::new()),
+        }
+    }
 }
 
 
@@ -15,43 +68,792 @@ pub(crate) enum _ServerInput {
     DoSomeWork,
 }
 
+// Header letting a client name a payload so later requests update it in place
+const CLIENT_ID_HEADER: &str = "X-Dbug-Id";
+
+// Hop-by-hop and length headers that must not be copied between connections
+const SKIPPED_HEADERS: [&str; 5] = ["host", "content-length", "connection", "transfer-encoding", "keep-alive"];
+
+/// Reasons a request body is refused
+#[derive(Debug)]
+enum BodyRejection {
+    TooLarge(u64),
+    UnsupportedEncoding(String),
+    UnsupportedContentType(String),
+    Malformed(String),
+}
+
+impl warp::reject::Reject for BodyRejection {}
+
+/// A browser page served from another host asked for a route only local tools may use
+#[derive(Debug)]
+struct ForeignOrigin(String);
+
+impl warp::reject::Reject for ForeignOrigin {}
+
+impl BodyRejection {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedEncoding(_) | Self::UnsupportedContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Malformed(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for BodyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(max_bytes) => write!(f, "Body exceeds the {max_bytes} byte limit"),
+            Self::UnsupportedEncoding(encoding) => write!(f, "Unsupported Content-Encoding: {encoding}"),
+            Self::UnsupportedContentType(content_type) => write!(f, "Unsupported Content-Type: {content_type}"),
+            Self::Malformed(reason) => write!(f, "Malformed body: {reason}"),
+        }
+    }
+}
+
+/// Parses a body as JSON, falling back to a plain string for non-JSON content
+fn body_to_value(body: &[u8]) -> Value {
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+/// Reads a body stream into memory, stopping as soon as it exceeds `max_bytes`
+async fn read_limited<S, B>(stream: S, max_bytes: u64) -> Result<Bytes, BodyRejection>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    let mut stream = std::pin::pin!(stream);
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let mut chunk = chunk.map_err(|e| BodyRejection::Malformed(e.to_string()))?;
+        if (body.len() + chunk.remaining()) as u64 > max_bytes {
+            return Err(BodyRejection::TooLarge(max_bytes));
+        }
+        while chunk.has_remaining() {
+            let bytes = chunk.chunk();
+            let len = bytes.len();
+            body.extend_from_slice(bytes);
+            chunk.advance(len);
+        }
+    }
+    Ok(Bytes::from(body))
+}
+
+/// Checks for the two-byte zlib header: deflate method and a valid check value
+fn has_zlib_header(body: &[u8]) -> bool {
+    body.len() >= 2
+        && body[0] & 0x0F == 8
+        && u16::from_be_bytes([body[0], body[1]]).is_multiple_of(31)
+}
+
+/// Decompresses a body according to its `Content-Encoding`, applying the size limit to the output
+fn decode_body(encoding: Option<&str>, body: &[u8], max_bytes: u64) -> Result<Vec<u8>, BodyRejection> {
+    let encoding = encoding.map(|e| e.trim().to_ascii_lowercase()).unwrap_or_default();
+    let reader: Box<dyn Read + '_> = match encoding.as_str() {
+        "" | "identity" => return Ok(body.to_vec()),
+        "gzip" | "x-gzip" => Box::new(flate2::read::MultiGzDecoder::new(body)),
+        // "deflate" should be zlib-wrapped, but some clients send a raw deflate stream
+        "deflate" if has_zlib_header(body) => Box::new(flate2::read::ZlibDecoder::new(body)),
+        "deflate" => Box::new(flate2::read::DeflateDecoder::new(body)),
+        "br" => Box::new(brotli::Decompressor::new(body, 4096)),
+        other => return Err(BodyRejection::UnsupportedEncoding(other.to_string())),
+    };
+
+    let mut decoded = Vec::new();
+    reader
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut decoded)
+        .map_err(|e| BodyRejection::Malformed(format!("failed to decode {encoding} body: {e}")))?;
+    if decoded.len() as u64 > max_bytes {
+        return Err(BodyRejection::TooLarge(max_bytes));
+    }
+    Ok(decoded)
+}
+
+/// Decodes a body for display, keeping the raw bytes when they cannot be decoded
+fn decoded_value(headers: &HeaderMap, body: &[u8], max_bytes: u64) -> Value {
+    let encoding = headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok());
+    decode_body(encoding, body, max_bytes)
+        .map_or_else(|_| body_to_value(body), |decoded| body_to_value(&decoded))
+}
+
+/// Extracts the raw request body, rejecting it once it exceeds `max_bytes`
+fn limited_body(max_bytes: u64) -> impl Filter<Extract = (Bytes,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<u64>("content-length")
+        .and(warp::body::stream())
+        .and_then(move |content_length: Option<u64>, body| async move {
+            if content_length.is_some_and(|length| length > max_bytes) {
+                return Err(warp::reject::custom(BodyRejection::TooLarge(max_bytes)));
+            }
+            read_limited(body, max_bytes).await.map_err(warp::reject::custom)
+        })
+}
+
+/// Extracts the request body, decompressed according to its `Content-Encoding`
+fn decoded_body(max_bytes: u64) -> impl Filter<Extract = (Vec<u8>,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("content-encoding")
+        .and(limited_body(max_bytes))
+        .and_then(move |encoding: Option<String>, body: Bytes| async move {
+            decode_body(encoding.as_deref(), &body, max_bytes).map_err(warp::reject::custom)
+        })
+}
+
+/// Extracts a possibly compressed JSON request body
+fn json_body(max_bytes: u64) -> impl Filter<Extract = (Value,), Error = warp::Rejection> + Clone {
+    decoded_body(max_bytes).and_then(|body: Vec<u8>| async move {
+        serde_json::from_slice(&body)
+            .map_err(|e| warp::reject::custom(BodyRejection::Malformed(e.to_string())))
+    })
+}
+
+/// Rejects OTLP/HTTP protobuf requests; only the JSON encoding is supported
+fn otlp_json() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("content-type")
+        .and_then(|content_type: Option<String>| async move {
+            match content_type {
+                Some(content_type) if content_type.contains("protobuf") => {
+                    Err(warp::reject::custom(BodyRejection::UnsupportedContentType(content_type)))
+                }
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+/// Parses a batch body: either a JSON array or newline-delimited JSON
+fn parse_batch(body: &[u8]) -> Result<Vec<Value>, BodyRejection> {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(values)) => Ok(values),
+        Ok(value) => Ok(vec![value]),
+        Err(_) => String::from_utf8_lossy(body)
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| BodyRejection::Malformed(format!("line {}: {e}", idx + 1)))
+            })
+            .collect(),
+    }
+}
+
+/// Whether an `Origin` header names a page served from this machine
+fn is_loopback_origin(origin: &str) -> bool {
+    let Ok(uri) = origin.parse::<warp::http::Uri>() else {
+        return false;
+    };
+    match uri.host() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+/// Refuses requests a browser makes on behalf of a page from another host
+///
+/// Tools like `dbug tail` and curl send no `Origin` header and are let through.
+fn local_origin() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and_then(|origin: Option<String>| async move {
+            match origin {
+                Some(origin) if !is_loopback_origin(&origin) => Err(warp::reject::custom(ForeignOrigin(origin))),
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+/// Replies to refused requests with their status code and reports them to the GUI
+async fn handle_rejection(
+    rejection: warp::Rejection,
+    mut output: futures::channel::mpsc::Sender<ServerMessage>,
+) -> Result<warp::reply::WithStatus<String>, warp::Rejection> {
+    let (reason, status) = if let Some(body_rejection) = rejection.find::<BodyRejection>() {
+        (body_rejection.to_string(), body_rejection.status())
+    } else if let Some(ForeignOrigin(origin)) = rejection.find() {
+        (format!("Refused request from {origin}: only local tools may use this route"), StatusCode::FORBIDDEN)
+    } else {
+        return Err(rejection);
+    };
+    let _ = output.send(ServerMessage::RequestRejected(reason.clone())).await;
+    Ok(warp::reply::with_status(reason, status))
+}
+
+/// The request's query string as sent, empty when there is none
+fn raw_query() -> impl Filter<Extract = (String,), Error = std::convert::Infallible> + Clone {
+    warp::query::raw().or(warp::any().map(String::new)).unify()
+}
+
+/// Relays a request to its upstream and returns the upstream's status, headers and body
+async fn forward(
+    client: &reqwest::Client,
+    route: &UpstreamRoute,
+    path: &str,
+    query: &str,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, HeaderMap, Bytes), reqwest::Error> {
+    let rest = path
+        .strip_prefix(route.path.trim_end_matches('/'))
+        .unwrap_or(path);
+    let mut url = format!("{}{rest}", route.url.trim_end_matches('/'));
+    if !query.is_empty() {
+        url = format!("{url}?{query}");
+    }
+
+    let mut request = client.post(url).body(body);
+    for (name, value) in headers {
+        if !SKIPPED_HEADERS.contains(&name.as_str()) {
+            request = request.header(name.as_str(), value.as_bytes());
+        }
+    }
+
+    let response = request.send().await?;
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut response_headers = HeaderMap::new();
+    for (name, value) in response.headers() {
+        if SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (
+            warp::http::header::HeaderName::from_bytes(name.as_str().as_bytes()),
+            warp::http::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            response_headers.append(name, value);
+        }
+    }
+    let response_body = response.bytes().await?;
+
+    Ok((status, response_headers, response_body))
+}
+
+/// Parses one line of a JSON-lines stream into a server message
+fn line_message(line: &[u8]) -> Option<ServerMessage> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    Some(match serde_json::from_str(line) {
+        Ok(value) => ServerMessage::PayloadReceived(protocol::payload(value)),
+        Err(e) => ServerMessage::RequestRejected(BodyRejection::Malformed(e.to_string()).to_string()),
+    })
+}
+
+/// Accepts newline-delimited JSON over TCP, one payload per line
+async fn listen_tcp(
+    addr: std::net::SocketAddr,
+    max_bytes: u64,
+    output: futures::channel::mpsc::Sender<ServerMessage>,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!("TCP listener started at {addr}");
+
+    loop {
+        let (socket, _peer) = listener.accept().await?;
+        let mut output = output.clone();
+        tokio::task::spawn(async move {
+            let mut reader = tokio::io::BufReader::new(socket);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                // Read one byte past the limit so an oversized line can be detected
+                match (&mut reader).take(max_bytes.saturating_add(1)).read_until(b'\n', &mut line).await {
+                    Ok(0) => break,
+                    Ok(_) if line.len() as u64 > max_bytes => {
+                        let reason = BodyRejection::TooLarge(max_bytes).to_string();
+                        let _ = output.send(ServerMessage::RequestRejected(reason)).await;
+                        break;
+                    }
+                    Ok(_) => {
+                        if let Some(message) = line_message(&line) {
+                            let _ = output.send(message).await;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("TCP connection error: {e}");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Accepts newline-delimited JSON over UDP; a datagram may carry several lines
+async fn listen_udp(
+    addr: std::net::SocketAddr,
+    mut output: futures::channel::mpsc::Sender<ServerMessage>,
+) -> std::io::Result<()> {
+    let socket = tokio::net::UdpSocket::bind(addr).await?;
+    eprintln!("UDP listener started at {addr}");
+
+    // Largest possible UDP datagram
+    let mut buf = vec![0u8; 65_535];
+    loop {
+        let (len, _peer) = socket.recv_from(&mut buf).await?;
+        for line in buf[..len].split(|byte| *byte == b'\n') {
+            if let Some(message) = line_message(line) {
+                let _ = output.send(message).await;
+            }
+        }
+    }
+}
+
+/// Binds a Unix socket at `path`, removing a stale socket left behind by a previous run
+///
+/// Anything else at `path` is left alone, so a mistyped setting cannot delete a file.
+#[cfg(unix)]
+fn bind_unix_socket(path: &std::path::Path) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "path exists and is not a socket",
+            ));
+        }
+        Ok(_) => {
+            // A socket that still accepts connections belongs to a running instance
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    "socket is in use by another process",
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    tokio::net::UnixListener::bind(path)
+}
+
+/// Serves every route with the saved settings, yielding what clients send
+pub fn listen() -> impl Stream<Item = ServerMessage> {
+    listen_with(Settings::load())
+}
+
+/// Serves every route with the given settings, e.g. on another port in tests
+pub fn listen_with(settings: Settings) -> impl Stream<Item = ServerMessage> {
+    let (subscriber, messages) = futures::channel::mpsc::channel(100);
+    // Serving never finishes; polling it alongside the messages is what keeps the server running
+    let server = futures::stream::once(serve(settings, subscriber)).filter_map(|()| async { None });
+    futures::stream::select(messages, server)
+}
+
+/// Runs the server, passing what clients send to `output`
+async fn serve(settings: Settings, output: futures::channel::mpsc::Sender<ServerMessage>) {
+
+    let host = settings.get_server_host();
+    let port = settings.get_server_port();
+    let max_body_bytes = settings.get_max_body_bytes();
+    let client = reqwest::Client::new();
+
+    // Only matches paths with a configured upstream, before the body is consumed
+    let proxy = warp::post()
+        .and(warp::path::full())
+        .and_then({
+            let settings = settings.clone();
+            move |path: FullPath| {
+                let route = settings.upstream_for(path.as_str()).cloned();
+                async move {
+                    route
+                        .map(|route| (route, path))
+                        .ok_or_else(warp::reject::not_found)
+                }
+            }
+        })
+        .untuple_one()
+        .and(raw_query())
+        .and(warp::header::headers_cloned())
+        .and(limited_body(max_body_bytes))
+        .and_then({
+            let output = output.clone();
+            move |route: UpstreamRoute, path: FullPath, query: String, headers: HeaderMap, body: Bytes| {
+                let mut output = output.clone();
+                let client = client.clone();
+                async move {
+                    let request = decoded_value(&headers, &body, max_body_bytes);
+                    let (reply, response) = match forward(&client, &route, path.as_str(), &query, &headers, body).await {
+                        Ok((status, headers, body)) => {
+                            let response = json!({
+                                "status": status.as_u16(),
+                                "body": decoded_value(&headers, &body, max_body_bytes),
+                            });
+                            let mut reply = warp::http::Response::new(warp::hyper::Body::from(body));
+                            *reply.status_mut() = status;
+                            *reply.headers_mut() = headers;
+                            (reply, response)
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to forward {} to {}: {e}", path.as_str(), route.url);
+                            let response = json!({
+                                "status": StatusCode::BAD_GATEWAY.as_u16(),
+                                "error": e.to_string(),
+                            });
+                            let mut reply = warp::http::Response::new(warp::hyper::Body::from(e.to_string()));
+                            *reply.status_mut() = StatusCode::BAD_GATEWAY;
+                            (reply, response)
+                        }
+                    };
+                    let _ = output.send(ServerMessage::ExchangeRecorded { request, response }).await;
+                    Ok::<_, warp::Rejection>(reply)
+                }
+            }
+        });
+
+    let batch = warp::post()
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(decoded_body(max_body_bytes))
+        .and_then({
+            let output = output.clone();
+            move |body: Vec<u8>| {
+                let mut output = output.clone();
+                async move {
+                    let values = parse_batch(&body).map_err(warp::reject::custom)?;
+                    let count = values.len();
+                    if count > 0 {
+                        let payloads = values.into_iter().map(protocol::payload).collect();
+                        let _ = output.send(ServerMessage::PayloadsReceived(payloads)).await;
+                    }
+                    Ok::<_, warp::Rejection>(format!("Received {count} payloads"))
+                }
+            }
+        });
+
+    // OpenTelemetry OTLP/HTTP receivers, JSON encoding only
+    let otlp = warp::post()
+        .and(warp::path!("v1" / "logs"))
+        .map(|| otlp::logs as fn(&Value) -> Vec<Payload>)
+        .or(warp::post()
+            .and(warp::path!("v1" / "traces"))
+            .map(|| otlp::traces as fn(&Value) -> Vec<Payload>))
+        .unify()
+        .and(otlp_json())
+        .and(json_body(max_body_bytes))
+        .and_then({
+            let output = output.clone();
+            move |unpack: fn(&Value) -> Vec<Payload>, body: Value| {
+                let mut output = output.clone();
+                async move {
+                    let payloads = unpack(&body);
+                    if !payloads.is_empty() {
+                        let _ = output.send(ServerMessage::PayloadsReceived(payloads)).await;
+                    }
+                    Ok::<_, warp::Rejection>(warp::reply::json(&json!({})))
+                }
+            }
+        });
+
+    // Sentry SDKs post single events to `store` and batches of items to `envelope`
+    let sentry_store = warp::post()
+        .and(warp::path!("api" / String / "store"))
+        .and(json_body(max_body_bytes))
+        .map(|_project: String, event: Value| vec![sentry::event(event)]);
+    let sentry_envelope = warp::post()
+        .and(warp::path!("api" / String / "envelope"))
+        .and(decoded_body(max_body_bytes))
+        .and_then(|_project: String, body: Vec<u8>| async move {
+            sentry::envelope(&body)
+                .map_err(|reason| warp::reject::custom(BodyRejection::Malformed(reason)))
+        });
+    let sentry = sentry_store
+        .or(sentry_envelope)
+        .unify()
+        .and_then({
+            let output = output.clone();
+            move |payloads: Vec<Payload>| {
+                let mut output = output.clone();
+                async move {
+                    // Sentry SDKs expect the id of the stored event back
+                    let event_id = payloads
+                        .first()
+                        .and_then(|payload| payload.value.get("event_id").cloned())
+                        .unwrap_or(Value::Null);
+                    if !payloads.is_empty() {
+                        let _ = output.send(ServerMessage::PayloadsReceived(payloads)).await;
+                    }
+                    Ok::<_, warp::Rejection>(warp::reply::json(&json!({ "id": event_id })))
+                }
+            }
+        });
+
+    let payload = warp::post()
+        .and(warp::header::optional::<String>(CLIENT_ID_HEADER))
+        .and(json_body(max_body_bytes))
+        .map({
+            let output = output.clone();
+            move |client_id: Option<String>, body: Value| {
+                let mut output_clone = output.clone();
+                // Spatie Ray clients post their own request format to the root path
+                let messages = ray::messages(&body).unwrap_or_else(|| {
+                    let payload = protocol::payload(body);
+                    // The header takes precedence over an id inside the body
+                    let payload = match client_id {
+                        Some(client_id) => payload.with_client_id(client_id),
+                        None => payload,
+                    };
+                    vec![ServerMessage::PayloadReceived(payload)]
+                });
+                tokio::task::spawn(async move {
+                    for message in messages {
+                        let _ = output_clone.send(message).await;
+                    }
+                });
+                "Hello!".to_string()
+            }
+        });
+
+
+
+    // Ray's `pause()` polls its lock until released; never hold a client up
+    let ray_locks = warp::get()
+        .and(warp::path!("locks" / String))
+        .map(|_name: String| warp::reply::json(&json!({ "active": false, "stop_execution": false })));
+
+    // Streams every payload stored in this process as a line of JSON until the client disconnects
+    let tail_route = warp::get()
+        .and(warp::path!("tail"))
+        .and(local_origin())
+        .map(move || {
+            let lines = futures::stream::unfold(storage::subscribe(), |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(payload) => {
+                            let line = serde_json::to_string(&payload).unwrap_or_default() + "\n";
+                            return Some((Ok::<_, std::convert::Infallible>(line), receiver));
+                        }
+                        // A slow client misses payloads rather than holding the server up
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            });
+            let mut response = warp::reply::Response::new(warp::hyper::Body::wrap_stream(lines));
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/x-ndjson"));
+            response
+        });
+
+    let clear = warp::delete()
+        .and(warp::path!("payloads"))
+        .and(local_origin())
+        .map({
+            let output = output.clone();
+            move || {
+                let mut output_clone = output.clone();
+                tokio::task::spawn(async move {
+                    let _ = output_clone.send(ServerMessage::ClearRequested).await;
+                });
+                "Cleared".to_string()
+            }
+        });
+
+    // Any page may send payloads, but only local tools may read or clear them
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_methods(&[Method::POST, Method::OPTIONS])
+        .allow_headers(vec!["Content-Type", "Authorization", "Accept", "Origin", "X-Requested-With", "X-Sentry-Auth", CLIENT_ID_HEADER])
+        .max_age(3600);
+
+    let rejected = {
+        let output = output.clone();
+        move |rejection| handle_rejection(rejection, output.clone())
+    };
+
+    // Recover each route on its own so a refused body is not retried against `payload`
+    let ingest = proxy
+        .recover(rejected.clone())
+        .or(otlp.recover(rejected.clone()))
+        .or(sentry.recover(rejected.clone()))
+        .or(batch.recover(rejected.clone()))
+        .or(payload)
+        .with(cors);
+    let routes = ingest
+        .or(ray_locks)
+        .or(tail_route)
+        .or(clear)
+        .recover(rejected);
+
+    eprintln!("Server started at http://{host}:{port}");
+    for route in settings.get_upstream_routes() {
+        eprintln!("Forwarding {} to {}", route.path, route.url);
+    }
+
+    let addr: std::net::SocketAddr = format!("{host}:{port}")
+        .parse()
+        .unwrap_or_else(|_| ([127, 0, 0, 1], DEFAULT_SERVER_PORT).into());
+
+    if let Some(tcp_port) = settings.get_tcp_port() {
+        let output = output.clone();
+        tokio::task::spawn(async move {
+            let tcp_addr = std::net::SocketAddr::new(addr.ip(), tcp_port);
+            if let Err(e) = listen_tcp(tcp_addr, max_body_bytes, output).await {
+                eprintln!("TCP listener on {tcp_addr} failed: {e}");
+            }
+        });
+    }
+
+    if let Some(udp_port) = settings.get_udp_port() {
+        let output = output.clone();
+        tokio::task::spawn(async move {
+            let udp_addr = std::net::SocketAddr::new(addr.ip(), udp_port);
+            if let Err(e) = listen_udp(udp_addr, output).await {
+                eprintln!("UDP listener on {udp_addr} failed: {e}");
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    if settings.is_unix_socket_enabled() {
+        let path = settings.get_unix_socket_path().to_path_buf();
+        match bind_unix_socket(&path) {
+            Ok(listener) => {
+                eprintln!("Unix socket listener started at {}", path.display());
+                let incoming = futures::stream::unfold(listener, |listener| async move {
+                    let connection = listener.accept().await.map(|(stream, _addr)| stream);
+                    Some((connection, listener))
+                });
+                tokio::task::spawn(warp::serve(routes.clone()).run_incoming(incoming));
+            }
+            Err(e) => eprintln!("Unix socket listener on {} failed: {e}", path.display()),
+        }
+    }
+
+    warp::serve(routes).run(addr).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const BODY: &[u8] = br#"{"message":"hello"}"#;
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn chunks(chunks: &[&'static [u8]]) -> impl Stream<Item = Result<Bytes, warp::Error>> {
+        futures::stream::iter(chunks.iter().map(|chunk| Ok(Bytes::from_static(chunk))).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn decode_body_passes_identity_through() {
+        assert_eq!(decode_body(None, BODY, 1024).unwrap(), BODY);
+        assert_eq!(decode_body(Some(" Identity "), BODY, 1024).unwrap(), BODY);
+    }
+
+    #[test]
+    fn decode_body_decompresses_each_encoding() {
+        assert_eq!(decode_body(Some("gzip"), &gzip(BODY), 1024).unwrap(), BODY);
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("deflate"), &zlib.finish().unwrap(), 1024).unwrap(), BODY);
+
+        let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        raw.write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("deflate"), &raw.finish().unwrap(), 1024).unwrap(), BODY);
+
+        let mut br = Vec::new();
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22).write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("br"), &br, 1024).unwrap(), BODY);
+    }
+
+    #[test]
+    fn decode_body_limits_the_decompressed_size() {
+        let bomb = gzip(&vec![b'0'; 64 * 1024]);
+        assert!(matches!(decode_body(Some("gzip"), &bomb, 1024), Err(BodyRejection::TooLarge(1024))));
+        assert_eq!(decode_body(Some("gzip"), &gzip(BODY), BODY.len() as u64).unwrap(), BODY);
+        assert_eq!(decode_body(Some("gzip"), &gzip(BODY), u64::MAX).unwrap(), BODY);
+    }
 
- pub fn listen() -> impl Stream<Item = ServerMessage> {
+    #[test]
+    fn decode_body_rejects_unknown_and_corrupt_bodies() {
+        assert!(matches!(
+            decode_body(Some("zstd"), BODY, 1024),
+            Err(BodyRejection::UnsupportedEncoding(encoding)) if encoding == "zstd"
+        ));
+        assert!(matches!(decode_body(Some("gzip"), BODY, 1024), Err(BodyRejection::Malformed(_))));
+    }
 
-     stream::channel(100, |output: futures::channel::mpsc::Sender<ServerMessage>| async move {
-         let settings = Settings::load();
-         let host = settings.get_server_host();
-         let port = settings.get_server_port();
-         let payload = warp::post()
-             .and(warp::body::json())
-             .map({
-                 let output = output.clone();
-                 move |body: Value| {
-                     let mut output_clone = output.clone();
-                     tokio::task::spawn(async move {
-                         let _ = output_clone.send(ServerMessage::PayloadReceived(body)).await;
-                     });
-                     "Hello!".to_string()
-                 }
-             });
+    #[test]
+    fn parse_batch_reads_arrays_single_values_and_ndjson() {
+        assert_eq!(parse_batch(br#"[{"a":1},"two",3]"#).unwrap(), [json!({"a": 1}), json!("two"), json!(3)]);
+        assert_eq!(parse_batch(br#"{"a":1}"#).unwrap(), [json!({"a": 1})]);
+        assert_eq!(parse_batch(b"{\"a\":1}\r\n\n  [2]\n\"three\"\n").unwrap(), [json!({"a": 1}), json!([2]), json!("three")]);
+        assert!(parse_batch(b"").unwrap().is_empty());
+    }
 
+    #[test]
+    fn parse_batch_names_the_malformed_line() {
+        let Err(BodyRejection::Malformed(reason)) = parse_batch(b"{\"a\":1}\n\n{\"b\":\n") else {
+            panic!("a truncated line was accepted");
+        };
+        assert!(reason.starts_with("line 3:"), "{reason}");
+        assert!(matches!(parse_batch(b"[1, 2"), Err(BodyRejection::Malformed(_))));
+        assert!(matches!(parse_batch(&[0xff, 0xfe, b'\n']), Err(BodyRejection::Malformed(_))));
+    }
 
+    #[tokio::test]
+    async fn read_limited_joins_chunks_within_the_limit() {
+        let body = read_limited(chunks(&[b"{\"message\":", b"\"hello\"}"]), BODY.len() as u64).await;
+        assert_eq!(body.unwrap(), BODY);
+    }
 
-         let cors = warp::cors()
-             .allow_any_origin()
-             .allow_methods(&[Method::POST, Method::OPTIONS])
-             .allow_headers(vec!["Content-Type", "Authorization", "Accept", "Origin", "X-Requested-With"])
-             .max_age(3600);
+    #[tokio::test]
+    async fn read_limited_stops_past_the_limit() {
+        let body = read_limited(chunks(&[b"{\"message\":", b"\"hello\"}"]), 12).await;
+        assert!(matches!(body, Err(BodyRejection::TooLarge(12))));
+    }
 
-         let routes = payload.with(cors);
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn bind_unix_socket_replaces_only_stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("dbug-socket-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
 
-         println!("Server started at http://{host}:{port}");
+        let file = dir.join("not-a-socket");
+        std::fs::write(&file, "keep me").unwrap();
+        let error = bind_unix_socket(&file).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
 
-         let addr: std::net::SocketAddr = format!("{host}:{port}")
-             .parse()
-             .unwrap_or_else(|_| ([127, 0, 0, 1], 53821).into());
+        let socket = dir.join("dbug.sock");
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        let listener = bind_unix_socket(&socket).unwrap();
+        assert_eq!(bind_unix_socket(&socket).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        drop(listener);
 
-         warp::serve(routes).run(addr).await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
 
-     })
+    #[test]
+    fn only_pages_on_this_machine_are_loopback_origins() {
+        for origin in ["http://localhost:3000", "http://127.0.0.1", "https://127.4.5.6:8443", "http://[::1]:8080"] {
+            assert!(is_loopback_origin(origin), "{origin}");
+        }
+        for origin in ["https://example.com", "http://localhost.example.com", "http://192.168.1.2", "null", ""] {
+            assert!(!is_loopback_origin(origin), "{origin}");
+        }
+    }
 }
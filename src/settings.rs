@@ -1,46 +1,93 @@
 use crate::storage::Storage;
-use iced::{Point, Size, Theme};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where the window was last placed on screen
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SerializablePoint {
     pub x: f32,
     pub y: f32,
 }
 
-impl From<Point> for SerializablePoint {
-    fn from(point: Point) -> Self {
-        Self {
-            x: point.x,
-            y: point.y,
+/// How large the window last was
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SerializableSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Something a global hotkey can do while the window is in the background
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    ShowWindow,
+    ClearPayloads,
+    TogglePause,
+    CopyLatestPayload,
+    TogglePinLatest,
+}
+
+impl HotkeyAction {
+    pub const ALL: [Self; 5] = [
+        Self::ShowWindow,
+        Self::ClearPayloads,
+        Self::TogglePause,
+        Self::CopyLatestPayload,
+        Self::TogglePinLatest,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::ShowWindow => "Toggle visibility from anywhere",
+            Self::ClearPayloads => "Clear payloads from anywhere",
+            Self::TogglePause => "Pause or resume the list",
+            Self::CopyLatestPayload => "Copy the latest payload's JSON",
+            Self::TogglePinLatest => "Pin or unpin the latest payload",
         }
     }
-}
 
-impl From<SerializablePoint> for Point {
-    fn from(val: SerializablePoint) -> Self {
-        Point::new(val.x, val.y)
+    /// Shortcut bound out of the box, in the format accepted by `HotKey::from_str`
+    pub fn default_shortcut(self) -> Option<&'static str> {
+        match self {
+            Self::ShowWindow => Some("shift+super+KeyL"),
+            Self::ClearPayloads => Some("shift+super+KeyK"),
+            _ => None,
+        }
     }
 }
 
+/// Forwards POSTs under a path prefix to an upstream server
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SerializableSize {
-    pub width: f32,
-    pub height: f32,
+pub struct UpstreamRoute {
+    /// Path prefix to match, e.g. "/api"
+    pub path: String,
+    /// Base URL the remainder of the path is appended to, e.g. "http://localhost:8080"
+    pub url: String,
 }
 
-impl From<Size> for SerializableSize {
-    fn from(size: Size) -> Self {
-        Self {
-            width: size.width,
-            height: size.height,
-        }
-    }
+/// What the list does when a new payload arrives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NewPayloadBehavior {
+    /// Expand the newest payload and scroll to the top
+    #[default]
+    ExpandNewest,
+    /// Keep the expanded payload and its place on screen
+    KeepSelection,
+    /// Expand the newest payload only when none is expanded
+    ExpandIfNone,
 }
 
-impl From<SerializableSize> for Size {
-    fn from(val: SerializableSize) -> Self {
-        Size::new(val.width, val.height)
+impl NewPayloadBehavior {
+    pub const ALL: [Self; 3] = [Self::ExpandNewest, Self::KeepSelection, Self::ExpandIfNone];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ExpandNewest => "Expand the newest payload",
+            Self::KeepSelection => "Keep the current selection",
+            Self::ExpandIfNone => "Expand the newest if none is expanded",
+        }
     }
 }
 
@@ -51,6 +98,69 @@ pub struct Settings {
     window_size: SerializableSize,
     server_host: String,
     server_port: u16,
+    #[serde(default)]
+    upstream_routes: Vec<UpstreamRoute>,
+    #[serde(default = "default_max_body_bytes", deserialize_with = "deserialize_max_body_bytes")]
+    max_body_bytes: u64,
+    /// Port for newline-delimited JSON over TCP, disabled when unset
+    #[serde(default)]
+    tcp_port: Option<u16>,
+    /// Port for newline-delimited JSON over UDP, disabled when unset
+    #[serde(default)]
+    udp_port: Option<u16>,
+    #[serde(default)]
+    unix_socket_enabled: bool,
+    #[serde(default = "default_unix_socket_path")]
+    unix_socket_path: PathBuf,
+    /// Move a payload to the top when a client updates it, instead of keeping its position
+    #[serde(default)]
+    move_updated_to_top: bool,
+    #[serde(default)]
+    new_payload_behavior: NewPayloadBehavior,
+    /// Global hotkey shortcuts by action; unbound actions are left out
+    #[serde(default = "default_hotkeys")]
+    hotkeys: BTreeMap<HotkeyAction, String>,
+}
+
+/// Host the server listens on out of the box, shared with the `dbug!` client
+pub const DEFAULT_SERVER_HOST: &str = dbug_client::DEFAULT_HOST;
+
+/// Port the server listens on out of the box, shared with the `dbug!` client
+pub const DEFAULT_SERVER_PORT: u16 = dbug_client::DEFAULT_PORT;
+
+/// Largest request body the server accepts, after decompression (2 MiB)
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 2 * 1024 * 1024;
+
+/// The body limits the server accepts; a zero limit would refuse every body
+pub const MAX_BODY_BYTES_RANGE: std::ops::RangeInclusive<u64> = 1024..=1024 * 1024 * 1024;
+
+fn default_max_body_bytes() -> u64 {
+    DEFAULT_MAX_BODY_BYTES
+}
+
+/// Reads the body limit from a config file edited by hand, bringing it into `MAX_BODY_BYTES_RANGE`
+fn deserialize_max_body_bytes<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    u64::deserialize(deserializer).map(clamp_max_body_bytes)
+}
+
+fn clamp_max_body_bytes(max_body_bytes: u64) -> u64 {
+    max_body_bytes.clamp(*MAX_BODY_BYTES_RANGE.start(), *MAX_BODY_BYTES_RANGE.end())
+}
+
+/// Hotkeys bound out of the box
+pub fn default_hotkeys() -> BTreeMap<HotkeyAction, String> {
+    HotkeyAction::ALL
+        .into_iter()
+        .filter_map(|action| Some((action, action.default_shortcut()?.to_string())))
+        .collect()
+}
+
+/// Unix socket path under the storage directory, next to config.json
+pub fn default_unix_socket_path() -> PathBuf {
+    Storage::config_path().with_file_name("dbug.sock")
 }
 
 impl Default for Settings {
@@ -62,29 +172,29 @@ impl Default for Settings {
                 width: 1280.0,
                 height: 800.0,
             },
-            server_host: "127.0.0.1".to_string(),
-            server_port: 53821,
+            server_host: DEFAULT_SERVER_HOST.to_string(),
+            server_port: DEFAULT_SERVER_PORT,
+            upstream_routes: Vec::new(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            tcp_port: None,
+            udp_port: None,
+            unix_socket_enabled: false,
+            unix_socket_path: default_unix_socket_path(),
+            move_updated_to_top: false,
+            new_payload_behavior: NewPayloadBehavior::default(),
+            hotkeys: default_hotkeys(),
         }
     }
 }
 
 impl Settings {
-    // Property to get the actual Theme
-    pub fn theme(&self) -> Theme {
-        // Find the theme by name in Theme::ALL, fallback to Dark
-        Theme::ALL
-            .iter()
-            .find(|t| t.to_string() == self.theme_name)
-            .cloned()
-            .unwrap_or_else(|| {
-                eprintln!("Theme '{}' not found, using Dark", self.theme_name);
-                Theme::Dark
-            })
+    /// Name of the window's color theme, e.g. "Catppuccin Mocha"
+    pub fn get_theme_name(&self) -> &str {
+        &self.theme_name
     }
 
-    // Method to set the theme
-    pub fn set_theme(&mut self, theme: Theme) {
-        self.theme_name = theme.to_string();
+    pub fn set_theme_name(&mut self, theme_name: String) {
+        self.theme_name = theme_name;
     }
 
     pub fn load() -> Self {
@@ -95,20 +205,20 @@ impl Settings {
         Storage::save_config(self).map_err(|e| e.to_string())
     }
 
-    pub fn set_window_position(&mut self, position: Point) {
-        self.window_position = position.into();
+    pub fn set_window_position(&mut self, position: SerializablePoint) {
+        self.window_position = position;
     }
 
-    pub fn set_window_size(&mut self, size: Size) {
-        self.window_size = size.into();
+    pub fn set_window_size(&mut self, size: SerializableSize) {
+        self.window_size = size;
     }
 
-    pub fn get_window_position(&self) -> Point {
-        self.window_position.clone().into()
+    pub fn get_window_position(&self) -> SerializablePoint {
+        self.window_position
     }
 
-    pub fn get_window_size(&self) -> Size {
-        self.window_size.clone().into()
+    pub fn get_window_size(&self) -> SerializableSize {
+        self.window_size
     }
 
     pub fn get_server_host(&self) -> &str {
@@ -126,4 +236,121 @@ impl Settings {
     pub fn set_server_port(&mut self, port: u16) {
         self.server_port = port;
     }
+
+    pub fn get_tcp_port(&self) -> Option<u16> {
+        self.tcp_port
+    }
+
+    pub fn set_tcp_port(&mut self, port: Option<u16>) {
+        self.tcp_port = port;
+    }
+
+    pub fn get_udp_port(&self) -> Option<u16> {
+        self.udp_port
+    }
+
+    pub fn set_udp_port(&mut self, port: Option<u16>) {
+        self.udp_port = port;
+    }
+
+    pub fn is_unix_socket_enabled(&self) -> bool {
+        self.unix_socket_enabled
+    }
+
+    pub fn set_unix_socket_enabled(&mut self, enabled: bool) {
+        self.unix_socket_enabled = enabled;
+    }
+
+    pub fn get_unix_socket_path(&self) -> &Path {
+        &self.unix_socket_path
+    }
+
+    pub fn set_unix_socket_path(&mut self, path: PathBuf) {
+        self.unix_socket_path = path;
+    }
+
+    pub fn is_move_updated_to_top(&self) -> bool {
+        self.move_updated_to_top
+    }
+
+    pub fn set_move_updated_to_top(&mut self, move_to_top: bool) {
+        self.move_updated_to_top = move_to_top;
+    }
+
+    pub fn get_new_payload_behavior(&self) -> NewPayloadBehavior {
+        self.new_payload_behavior
+    }
+
+    pub fn set_new_payload_behavior(&mut self, behavior: NewPayloadBehavior) {
+        self.new_payload_behavior = behavior;
+    }
+
+    pub fn get_hotkey(&self, action: HotkeyAction) -> Option<&str> {
+        self.hotkeys.get(&action).map(String::as_str)
+    }
+
+    pub fn get_hotkeys(&self) -> &BTreeMap<HotkeyAction, String> {
+        &self.hotkeys
+    }
+
+    pub fn set_hotkey(&mut self, action: HotkeyAction, shortcut: Option<String>) {
+        match shortcut {
+            Some(shortcut) => self.hotkeys.insert(action, shortcut),
+            None => self.hotkeys.remove(&action),
+        };
+    }
+
+    pub fn get_max_body_bytes(&self) -> u64 {
+        self.max_body_bytes
+    }
+
+    /// Sets the body limit, brought into `MAX_BODY_BYTES_RANGE`
+    pub fn set_max_body_bytes(&mut self, max_body_bytes: u64) {
+        self.max_body_bytes = clamp_max_body_bytes(max_body_bytes);
+    }
+
+    pub fn get_upstream_routes(&self) -> &[UpstreamRoute] {
+        &self.upstream_routes
+    }
+
+    pub fn set_upstream_routes(&mut self, routes: Vec<UpstreamRoute>) {
+        self.upstream_routes = routes;
+    }
+
+    /// Finds the upstream route with the longest prefix matching `path`
+    pub fn upstream_for(&self, path: &str) -> Option<&UpstreamRoute> {
+        self.upstream_routes
+            .iter()
+            .filter(|route| {
+                let prefix = route.path.trim_end_matches('/');
+                path == prefix || path.starts_with(&format!("{prefix}/"))
+            })
+            .max_by_key(|route| route.path.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_max_body_bytes(max_body_bytes: u64) -> Settings {
+        let mut config = serde_json::to_value(Settings::default()).unwrap();
+        config["max_body_bytes"] = max_body_bytes.into();
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn out_of_range_body_limits_are_clamped_when_loading() {
+        assert_eq!(with_max_body_bytes(0).get_max_body_bytes(), *MAX_BODY_BYTES_RANGE.start());
+        assert_eq!(with_max_body_bytes(u64::MAX).get_max_body_bytes(), *MAX_BODY_BYTES_RANGE.end());
+        assert_eq!(with_max_body_bytes(4096).get_max_body_bytes(), 4096);
+    }
+
+    #[test]
+    fn missing_body_limit_uses_the_default() {
+        let mut config = serde_json::to_value(Settings::default()).unwrap();
+        config.as_object_mut().unwrap().remove("max_body_bytes");
+        let settings: Settings = serde_json::from_value(config).unwrap();
+        assert_eq!(settings.get_max_body_bytes(), DEFAULT_MAX_BODY_BYTES);
+    }
 }
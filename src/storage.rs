@@ -1,40 +1,338 @@
 use chrono::Utc;
-use serde_json::Value;
+use futures::{Stream, StreamExt};
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::hash_map::DefaultHasher;
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
 
 // Define the final storage limit (2 MiB)
 const MAX_STORAGE_BYTES: u64 = 2 * 1024 * 1024;
 
+// Previous values kept for each payload updated in place
+const MAX_REVISIONS: usize = 20;
+
+// How often a store that cannot be watched is checked for changes
+const UNWATCHED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Stored payloads kept for subscribers that fall behind
+const SUBSCRIBER_BUFFER: usize = 256;
+
+/// Payloads as the stores in this process saved them
+static STORED: OnceLock<broadcast::Sender<Payload>> = OnceLock::new();
+
+fn stored_sender() -> &'static broadcast::Sender<Payload> {
+    STORED.get_or_init(|| broadcast::channel(SUBSCRIBER_BUFFER).0)
+}
+
+/// Receives every payload any `Storage` in this process adds or updates, with its final id
+///
+/// This is what `/tail` streams, so what it prints can be found again with `dbug show`.
+pub fn subscribe() -> broadcast::Receiver<Payload> {
+    stored_sender().subscribe()
+}
+
+/// Yields whenever the store in `storage_dir` may have been changed, e.g. by another process
+///
+/// Front-ends call `reload_if_changed` for each item; it also yields for this process's own saves,
+/// which that sees through. Where the directory cannot be watched, this yields every second instead.
+pub fn watch(storage_dir: PathBuf) -> impl Stream<Item = ()> {
+    let (sender, receiver) = futures::channel::mpsc::unbounded();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // Saves replace `data.json` and then count themselves in `data.lock`; merely opening or
+        // reading either one, as checking for changes does, is no change
+        let changed = event.is_ok_and(|event| {
+            !event.kind.is_access()
+                && event.paths.iter().any(|path| path.ends_with("data.json") || path.ends_with("data.lock"))
+        });
+        if changed {
+            let _ = sender.unbounded_send(());
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(&storage_dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+
+    match watcher {
+        // The watcher stops when dropped, so the stream keeps it
+        Ok(watcher) => receiver
+            .map(move |()| {
+                let _ = &watcher;
+            })
+            .left_stream(),
+        Err(e) => {
+            eprintln!("WARN: Failed to watch {}, checking it every second instead: {e}", storage_dir.display());
+            futures::stream::unfold((), |()| async {
+                tokio::time::sleep(UNWATCHED_CHECK_INTERVAL).await;
+                Some(((), ()))
+            })
+            .right_stream()
+        }
+    }
+}
+
 /// Helper function to estimate the size of a JSON value in bytes
 /// Uses compact JSON representation length as an estimate.
 fn estimate_payload_size(value: &Value) -> u64 {
     serde_json::to_string(value).unwrap_or_default().len() as u64
 }
 
+/// A single stored payload and anything recorded alongside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payload {
+    pub id: String,
+    pub value: Value,
+    /// Upstream response when the payload was relayed through a proxy route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    /// Short facts about the payload shown in its header, e.g. severity or trace id
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub meta: Map<String, Value>,
+    /// Identifier chosen by the sending client, used to update the payload later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Timestamp of the latest update in place, when the payload has been updated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    /// Values this payload held before being updated in place, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revisions: Vec<Revision>,
+    /// Identical payloads received right after this one and collapsed into it
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub repeats: u64,
+    /// Timestamp of the latest identical payload, when repeats were collapsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+    /// Pinned payloads are kept when the size limit is reached; clearing still deletes them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+/// A previous value of a payload that was updated in place
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    /// Timestamp the value was received, in the same format as payload IDs
+    pub received: String,
+    pub value: Value,
+}
+
+impl Payload {
+    /// Creates a payload stamped with the current time
+    pub fn new(value: Value) -> Self {
+        Self::with_id(Utc::now().timestamp_millis().to_string(), value)
+    }
+
+    fn with_id(id: String, value: Value) -> Self {
+        Self {
+            id,
+            value,
+            response: None,
+            meta: Map::new(),
+            client_id: None,
+            updated: None,
+            revisions: Vec::new(),
+            repeats: 0,
+            last_seen: None,
+            pinned: false,
+        }
+    }
+
+    /// Adds a metadata entry, skipping null values
+    pub fn with_meta(mut self, key: &str, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        if !value.is_null() {
+            self.meta.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    /// Tags the payload with the sending client's identifier
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// The payload as shown to users; relayed payloads pair the request with the upstream response
+    pub fn document(&self) -> Value {
+        match &self.response {
+            Some(response) => serde_json::json!({ "request": self.value, "response": response }),
+            None => self.value.clone(),
+        }
+    }
+
+    /// Timestamp of the payload's current value
+    pub fn received(&self) -> &str {
+        self.updated
+            .as_deref()
+            .or(self.last_seen.as_deref())
+            .unwrap_or(&self.id)
+    }
+
+    /// Number of times this payload was received, counting collapsed repeats
+    pub fn count(&self) -> u64 {
+        self.repeats + 1
+    }
+
+    /// Hash of the received content, ignoring the ID and any history
+    fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.value.to_string().hash(&mut hasher);
+        self.response.as_ref().map(Value::to_string).hash(&mut hasher);
+        Value::Object(self.meta.clone()).to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Replaces this payload's content with `newer`, keeping the old value as a revision
+    fn update_from(&mut self, newer: Payload) {
+        let received = self.received().to_string();
+        let previous = std::mem::replace(&mut self.value, newer.value);
+        self.revisions.push(Revision { received, value: previous });
+        if self.revisions.len() > MAX_REVISIONS {
+            self.revisions.remove(0);
+        }
+        self.updated = Some(newer.id);
+        self.response = newer.response;
+        self.meta = newer.meta;
+    }
+
+    /// Estimated size of the payload in bytes, including any response, metadata and revisions
+    fn estimated_size(&self) -> u64 {
+        estimate_payload_size(&self.value)
+            + self.response.as_ref().map_or(0, estimate_payload_size)
+            + self.meta.values().map(estimate_payload_size).sum::<u64>()
+            + self.revisions.iter().map(|revision| estimate_payload_size(&revision.value)).sum::<u64>()
+    }
+}
+
 // Type alias for the data stored within the Mutex
-type StorageState = (Vec<(String, Value, u64)>, u64);
+type StorageState = (Vec<(Payload, u64)>, u64);
+
+/// When the data file was last written, how large it was and how many saves `data.lock` counted,
+/// to notice writes by other processes
+///
+/// The count catches saves the timestamp alone would miss: two saves within the file system's
+/// timestamp resolution that leave the file the same length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+    generation: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path, lock: &File) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+            generation: read_generation(lock),
+        })
+    }
+}
+
+/// The number of saves recorded in `data.lock`, 0 before the first one
+fn read_generation(mut lock: &File) -> u64 {
+    let mut contents = String::new();
+    if lock.seek(SeekFrom::Start(0)).is_err() || lock.read_to_string(&mut contents).is_err() {
+        return 0;
+    }
+    contents.trim().parse().unwrap_or(0)
+}
+
+/// Records a save in `data.lock`, which must be locked exclusively
+fn bump_generation(mut lock: &File) -> io::Result<()> {
+    let generation = read_generation(lock).wrapping_add(1);
+    lock.set_len(0)?;
+    lock.seek(SeekFrom::Start(0))?;
+    write!(lock, "{generation}")
+}
+
+/// Reads stored payloads and their total size, accepting the formats older versions wrote
+fn read_data_file(data_file: &Path) -> io::Result<StorageState> {
+    if !data_file.exists() {
+        return Ok((Vec::new(), 0));
+    }
+    let mut contents = String::new();
+    File::open(data_file)?.read_to_string(&mut contents)?;
+
+    // Older versions stored `(id, value)` or `(id, value, size)` tuples
+    let loaded = serde_json::from_str::<Vec<(String, Value)>>(&contents)
+        .map(|old_data| {
+            old_data
+                .into_iter()
+                .map(|(id, value)| Payload::with_id(id, value))
+                .collect()
+        })
+        .or_else(|_| {
+            serde_json::from_str::<Vec<(String, Value, u64)>>(&contents).map(|old_data| {
+                old_data
+                    .into_iter()
+                    .map(|(id, value, _size)| Payload::with_id(id, value))
+                    .collect()
+            })
+        })
+        .or_else(|_| serde_json::from_str::<Vec<Payload>>(&contents))?;
+
+    let mut payloads = Vec::with_capacity(loaded.len());
+    let mut total_bytes: u64 = 0;
+    for payload in loaded {
+        let size = payload.estimated_size();
+        total_bytes += size;
+        payloads.push((payload, size));
+    }
+    Ok((payloads, total_bytes))
+}
 
 /// Storage struct to manage data persistence
+///
+/// Several processes may share one storage directory, e.g. the app and `dbug send`. Each change
+/// is made with `data.lock` held and starts from the latest saved payloads, so no process
+/// overwrites what another one saved.
 #[derive(Clone)]
 pub struct Storage {
     // Use the type alias for clarity
     data: Arc<Mutex<StorageState>>,
+    /// The data file as this store last read or wrote it
+    file_stamp: Arc<Mutex<Option<FileStamp>>>,
     storage_dir: PathBuf,
+    move_updated_to_top: bool,
 }
 
 impl Storage {
     /// Creates a new Storage instance
     pub fn new() -> io::Result<Self> {
-        // Inlined logic from storage_dir_path()
-        let storage_dir = dirs::home_dir()
+        Self::in_dir(Self::default_dir()?)
+    }
+
+    /// `~/.dbug_desktop`, where the app keeps its payloads and settings
+    pub fn default_dir() -> io::Result<PathBuf> {
+        dirs::home_dir()
             .map(|mut path| {
                 path.push(".dbug_desktop");
                 path
             })
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))?;
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))
+    }
+
+    /// The directory this store keeps its payloads in
+    pub fn dir(&self) -> &Path {
+        &self.storage_dir
+    }
+
+    /// Opens the store kept in `storage_dir` instead of `~/.dbug_desktop`, e.g. for tests
+    pub fn in_dir(storage_dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let storage_dir = storage_dir.into();
 
         // Inlined logic from ensure_storage_dir()
         if !storage_dir.exists() {
@@ -42,52 +340,110 @@ impl Storage {
         }
 
         let data_file = storage_dir.join("data.json");
-        let (initial_payloads, initial_total_bytes) = if data_file.exists() {
-            let mut file = File::open(&data_file)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-
-            match serde_json::from_str::<Vec<(String, Value)>>(&contents) {
-                Ok(old_data) => {
-                    let mut new_data = Vec::with_capacity(old_data.len());
-                    let mut total_bytes: u64 = 0;
-                    for (id, value) in old_data {
-                        let size = estimate_payload_size(&value);
-                        total_bytes += size;
-                        new_data.push((id, value, size));
-                    }
-                    (new_data, total_bytes)
-                }
-                Err(_) => {
-                    match serde_json::from_str::<Vec<(String, Value, u64)>>(&contents) {
-                        Ok(parsed_data) => {
-                            let total_bytes = parsed_data.iter().map(|(_, _, size)| size).sum();
-                            eprintln!(
-                                "INFO: Loaded {} existing payloads, total size: {} bytes",
-                                parsed_data.len(),
-                                total_bytes
-                            );
-                            (parsed_data, total_bytes)
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "WARN: Failed to parse data.json (both formats), starting fresh: {e}",
-                            );
-                            (Vec::new(), 0)
-                        }
-                    }
+        let lock = Self::lock_file(&storage_dir)?;
+        lock.lock_shared()?;
+        let file_stamp = FileStamp::of(&data_file, &lock);
+        let (initial_payloads, initial_total_bytes) = match read_data_file(&data_file) {
+            Ok((payloads, total_bytes)) => {
+                if file_stamp.is_some() {
+                    eprintln!(
+                        "INFO: Loaded {} existing payloads, total size: {} bytes",
+                        payloads.len(),
+                        total_bytes
+                    );
                 }
+                (payloads, total_bytes)
+            }
+            Err(e) => {
+                eprintln!("WARN: Failed to parse data.json, starting fresh: {e}");
+                (Vec::new(), 0)
             }
-        } else {
-            (Vec::new(), 0)
         };
 
         Ok(Self {
             data: Arc::new(Mutex::new((initial_payloads, initial_total_bytes))),
+            file_stamp: Arc::new(Mutex::new(file_stamp)),
             storage_dir,
+            move_updated_to_top: false,
         })
     }
 
+    fn data_file(&self) -> PathBuf {
+        self.storage_dir.join("data.json")
+    }
+
+    /// The file that processes sharing `storage_dir` lock while they read or change the data file
+    fn lock_file(storage_dir: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(storage_dir.join("data.lock"))
+    }
+
+    /// Locks the store for a change, first picking up anything another process saved
+    ///
+    /// Other processes wait until the returned file is dropped.
+    fn lock(&self) -> io::Result<(MutexGuard<'_, StorageState>, File)> {
+        let lock = Self::lock_file(&self.storage_dir)?;
+        lock.lock()?;
+        let mut data_guard = self.data.lock().map_err(|poisoned| {
+            eprintln!("ERROR: Storage mutex poisoned: {poisoned}");
+            io::Error::other("Mutex poisoned")
+        })?;
+        self.reload(&mut data_guard, &lock);
+        Ok((data_guard, lock))
+    }
+
+    /// Replaces the payloads in memory with the data file's if it changed since it was last read or written
+    fn reload(&self, data: &mut StorageState, lock: &File) -> bool {
+        let Ok(mut file_stamp) = self.file_stamp.lock() else {
+            return false;
+        };
+        let data_file = self.data_file();
+        let current = FileStamp::of(&data_file, lock);
+        if *file_stamp == current {
+            return false;
+        }
+        *file_stamp = current;
+
+        match read_data_file(&data_file) {
+            Ok(state) => {
+                *data = state;
+                eprintln!("INFO: Reloaded {} payloads saved by another process", data.0.len());
+                true
+            }
+            // Saving again replaces the unreadable file with the payloads kept in memory
+            Err(e) => {
+                eprintln!("WARN: Failed to reload data.json, keeping the payloads in memory: {e}");
+                false
+            }
+        }
+    }
+
+    /// Picks up payloads another process saved, returning whether the stored payloads changed
+    ///
+    /// Front-ends call this periodically to show changes made by e.g. `dbug send` or `dbug clear`.
+    pub fn reload_if_changed(&self) -> io::Result<bool> {
+        // Checking the file's stamp first avoids locking while nothing changed
+        let lock = Self::lock_file(&self.storage_dir)?;
+        let unchanged = self
+            .file_stamp
+            .lock()
+            .is_ok_and(|file_stamp| *file_stamp == FileStamp::of(&self.data_file(), &lock));
+        if unchanged {
+            return Ok(false);
+        }
+
+        lock.lock_shared()?;
+        let mut data_guard = self.data.lock().map_err(|poisoned| {
+            eprintln!("ERROR: Storage mutex poisoned in reload_if_changed: {poisoned}");
+            io::Error::other("Mutex poisoned")
+        })?;
+        Ok(self.reload(&mut data_guard, &lock))
+    }
+
     pub fn config_path() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -158,42 +514,136 @@ impl Storage {
         }
     }
 
-    /// Adds a JSON value to the storage, enforcing size limit
-    pub fn add_json(&self, json: &Value) -> io::Result<()> {
-        let id = Utc::now().timestamp_millis().to_string();
-        let new_payload_size = estimate_payload_size(json);
+    /// Adds a relayed request together with the upstream response, returning it as stored
+    pub fn add_exchange(&self, request: &Value, response: &Value) -> io::Result<Option<Payload>> {
+        let mut payload = Payload::new(request.clone());
+        payload.response = Some(response.clone());
+        self.add_payload(payload)
+    }
 
-        match self.data.lock() {
-            Ok(mut data_guard) => {
-                let (payloads, current_total_bytes) = &mut *data_guard;
-
-                // Enforce size limit - remove oldest entries first
-                while !payloads.is_empty() &&
-                      *current_total_bytes + new_payload_size > MAX_STORAGE_BYTES
-                {
-                    let removed_payload = payloads.remove(0);
-                    let removed_size = removed_payload.2;
-                    *current_total_bytes = current_total_bytes.saturating_sub(removed_size);
+    /// Adds a payload to the storage, enforcing size limit
+    ///
+    /// Returns the payload as stored, or `None` when it did not fit beside the pinned payloads.
+    pub fn add_payload(&self, payload: Payload) -> io::Result<Option<Payload>> {
+        Ok(self.add_payloads(vec![payload])?.pop())
+    }
+
+    /// Chooses whether payloads updated in place move to the top or keep their position
+    pub fn set_move_updated_to_top(&mut self, move_to_top: bool) {
+        self.move_updated_to_top = move_to_top;
+    }
+
+    /// Adds many payloads with a single save, enforcing size limit
+    ///
+    /// A payload whose client ID matches an existing payload updates it in place instead,
+    /// and a payload identical to the newest one only bumps its repeat count. Returns each payload
+    /// as it was stored, with its final ID, leaving out payloads that were dropped.
+    pub fn add_payloads(&self, new_payloads: Vec<Payload>) -> io::Result<Vec<Payload>> {
+        let (mut data_guard, lock) = self.lock()?;
+        let (payloads, current_total_bytes) = &mut *data_guard;
+        let mut stored = Vec::with_capacity(new_payloads.len());
+        // Payloads updated in place keep their IDs, so the newest ID need not be the last one's
+        let mut max_id = payloads.iter().filter_map(|(payload, _)| payload.id.parse::<i64>().ok()).max();
+        // Kept up to date below rather than summed again for every payload of a large batch
+        let mut pinned_bytes: u64 = payloads.iter().filter(|(stored, _)| stored.pinned).map(|(_, size)| size).sum();
+
+        for mut payload in new_payloads {
+            // IDs double as millisecond timestamps; keep them unique within the same millisecond
+            if let Ok(mut id) = payload.id.parse::<i64>() {
+                if let Some(max_id) = max_id.filter(|max_id| id <= *max_id) {
+                    id = max_id + 1;
+                    payload.id = id.to_string();
+                }
+                max_id = Some(id);
+            }
+
+            // Collapse a repeat of the newest payload into it rather than storing it again
+            if payload.client_id.is_none() {
+                if let Some((last, _)) = payloads.last_mut() {
+                    if last.client_id.is_none() && last.content_hash() == payload.content_hash() {
+                        last.repeats += 1;
+                        last.last_seen = Some(payload.id);
+                        stored.push(last.clone());
+                        continue;
+                    }
                 }
+            }
 
-                payloads.push((id.clone(), json.clone(), new_payload_size));
-                *current_total_bytes += new_payload_size;
+            // Take an existing payload with the same client ID out of the list to update it
+            let existing_idx = payload.client_id.as_ref().and_then(|client_id| {
+                payloads.iter().rposition(|(existing, _)| existing.client_id.as_ref() == Some(client_id))
+            });
+            let mut insert_idx = None;
+            let mut original = None;
+            if let Some(idx) = existing_idx {
+                let (mut existing, existing_size) = payloads.remove(idx);
+                *current_total_bytes = current_total_bytes.saturating_sub(existing_size);
+                if existing.pinned {
+                    pinned_bytes = pinned_bytes.saturating_sub(existing_size);
+                }
+                original = Some((idx, existing.clone(), existing_size));
+                existing.update_from(payload);
+                payload = existing;
+                if !self.move_updated_to_top {
+                    insert_idx = Some(idx);
+                }
             }
-            Err(poisoned) => {
-                eprintln!("ERROR: Storage mutex poisoned in add_json: {poisoned}");
-                return Err(io::Error::other("Mutex poisoned"));
+
+            let new_payload_size = payload.estimated_size();
+
+            // Pinned payloads are never evicted, so what does not fit beside them is dropped
+            if pinned_bytes + new_payload_size > MAX_STORAGE_BYTES {
+                eprintln!(
+                    "WARN: Dropping payload ({}) of {new_payload_size} bytes, it does not fit beside {pinned_bytes} bytes of pinned payloads",
+                    payload.id
+                );
+                // An update that is dropped leaves the payload as it was
+                if let Some((idx, original, original_size)) = original {
+                    if original.pinned {
+                        pinned_bytes += original_size;
+                    }
+                    payloads.insert(idx, (original, original_size));
+                    *current_total_bytes += original_size;
+                }
+                continue;
+            }
+
+            // Enforce size limit - remove oldest unpinned entries first
+            while *current_total_bytes + new_payload_size > MAX_STORAGE_BYTES {
+                let Some(oldest_idx) = payloads.iter().position(|(stored, _)| !stored.pinned) else {
+                    break;
+                };
+                let removed_payload = payloads.remove(oldest_idx);
+                let removed_size = removed_payload.1;
+                *current_total_bytes = current_total_bytes.saturating_sub(removed_size);
+                insert_idx = insert_idx.map(|idx| if oldest_idx < idx { idx - 1 } else { idx });
+            }
+
+            if payload.pinned {
+                pinned_bytes += new_payload_size;
+            }
+            stored.push(payload.clone());
+            match insert_idx {
+                Some(idx) => payloads.insert(idx.min(payloads.len()), (payload, new_payload_size)),
+                None => payloads.push((payload, new_payload_size)),
             }
+            *current_total_bytes += new_payload_size;
         }
 
-        self.save_to_file()
+        self.save(&data_guard, &lock)?;
+        for payload in &stored {
+            // Sending only fails while nobody is subscribed
+            let _ = stored_sender().send(payload.clone());
+        }
+        Ok(stored)
     }
 
     /// Retrieves all stored data, mapping away the internal size
-    pub fn get_all(&self) -> Vec<(String, Value)> {
+    pub fn get_all(&self) -> Vec<Payload> {
         match self.data.lock() {
             Ok(data_guard) => {
                 // Map to exclude the size before reversing and cloning
-                data_guard.0.iter().map(|(id, value, _size)| (id.clone(), value.clone())).rev().collect()
+                data_guard.0.iter().map(|(payload, _size)| payload.clone()).rev().collect()
             }
             Err(poisoned) => {
                 eprintln!("ERROR: Storage mutex poisoned in get_all: {poisoned}");
@@ -202,91 +652,377 @@ impl Storage {
         }
     }
 
-    /// Deletes an item by ID
-    pub fn delete(&self, id: &str) -> io::Result<bool> {
-        let mut requires_save = false;
-        let mut deletion_happened = false;
-        {
-            let mut data_guard = match self.data.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => {
-                    eprintln!("ERROR: Storage mutex poisoned in delete: {poisoned}");
-                    return Err(io::Error::other("Mutex poisoned"));
-                }
-            };
-            let (payloads, current_total_bytes) = &mut *data_guard;
-            let len_before = payloads.len();
-            let mut bytes_freed = 0;
+    /// Applies `update` to every payload sent with `client_id`, returning whether any matched
+    pub fn update_by_client_id(&self, client_id: &str, update: impl Fn(&mut Payload)) -> io::Result<bool> {
+        let mut updated = false;
+        let (mut data_guard, lock) = self.lock()?;
+        let (payloads, current_total_bytes) = &mut *data_guard;
+        for (payload, size) in payloads.iter_mut() {
+            if payload.client_id.as_deref() == Some(client_id) {
+                update(payload);
+                let new_size = payload.estimated_size();
+                *current_total_bytes = current_total_bytes.saturating_sub(*size) + new_size;
+                *size = new_size;
+                updated = true;
+            }
+        }
 
-            if let Some(payload_to_remove) = payloads.iter().find(|(item_id, _, _)| item_id == id) {
-                 bytes_freed = payload_to_remove.2;
+        if updated {
+            self.save(&data_guard, &lock)?;
+        }
+        Ok(updated)
+    }
+
+    /// Pins or unpins the payload with the given ID, returning whether it is now pinned
+    pub fn toggle_pin(&self, id: &str) -> io::Result<bool> {
+        let (mut data_guard, lock) = self.lock()?;
+        let pinned = match data_guard.0.iter_mut().find(|(payload, _)| payload.id == id) {
+            Some((payload, _)) => {
+                payload.pinned = !payload.pinned;
+                payload.pinned
             }
+            None => return Ok(false),
+        };
 
-            payloads.retain(|(item_id, _, _)| item_id != id);
-            let deleted_now = payloads.len() < len_before;
+        self.save(&data_guard, &lock)?;
+        Ok(pinned)
+    }
 
-            if deleted_now {
-                *current_total_bytes = current_total_bytes.saturating_sub(bytes_freed);
-                 eprintln!("INFO: Deleted payload ({}), freed {} bytes. New total: {}", id, bytes_freed, *current_total_bytes);
-                 requires_save = true;
-                 deletion_happened = true;
+    /// Deletes every payload sent with `client_id`
+    pub fn delete_by_client_id(&self, client_id: &str) -> io::Result<bool> {
+        let (mut data_guard, lock) = self.lock()?;
+        let (payloads, current_total_bytes) = &mut *data_guard;
+        let len_before = payloads.len();
+        let mut bytes_freed = 0;
+        payloads.retain(|(payload, size)| {
+            let matches = payload.client_id.as_deref() == Some(client_id);
+            if matches {
+                bytes_freed += size;
             }
-        } // Mutex guard dropped here
+            !matches
+        });
+        let deleted = len_before - payloads.len();
+
+        if deleted > 0 {
+            *current_total_bytes = current_total_bytes.saturating_sub(bytes_freed);
+            eprintln!("INFO: Deleted {deleted} payloads sent as {client_id}, freed {bytes_freed} bytes. New total: {}", *current_total_bytes);
+            self.save(&data_guard, &lock)?;
+        }
+
+        Ok(deleted > 0)
+    }
+
+    /// Deletes an item by ID
+    pub fn delete(&self, id: &str) -> io::Result<bool> {
+        let (mut data_guard, lock) = self.lock()?;
+        let (payloads, current_total_bytes) = &mut *data_guard;
+        let len_before = payloads.len();
+        let mut bytes_freed = 0;
+
+        if let Some(payload_to_remove) = payloads.iter().find(|(payload, _)| payload.id == id) {
+            bytes_freed = payload_to_remove.1;
+        }
+
+        payloads.retain(|(payload, _)| payload.id != id);
+        let deleted = payloads.len() < len_before;
 
-        if requires_save {
-            self.save_to_file()?;
+        if deleted {
+            *current_total_bytes = current_total_bytes.saturating_sub(bytes_freed);
+            eprintln!("INFO: Deleted payload ({}), freed {} bytes. New total: {}", id, bytes_freed, *current_total_bytes);
+            self.save(&data_guard, &lock)?;
         }
 
-        Ok(deletion_happened)
+        Ok(deleted)
     }
 
-    /// Deletes all stored data
+    /// Deletes all stored data, pinned payloads included
     pub fn delete_all(&self) -> io::Result<()> {
-        match self.data.lock() {
-            Ok(mut data_guard) => {
-                let (payloads, current_total_bytes) = &mut *data_guard;
-                if !payloads.is_empty() {
-                     eprintln!("INFO: Clearing all {} payloads, freeing {} bytes.", payloads.len(), *current_total_bytes);
-                     payloads.clear();
-                     *current_total_bytes = 0;
-                } else {
-                    eprintln!("INFO: delete_all called but no payloads to clear.");
-                }
-            }
-            Err(poisoned) => {
-                eprintln!("ERROR: Storage mutex poisoned in delete_all: {poisoned}");
-                return Err(io::Error::other("Mutex poisoned"));
-            }
+        let (mut data_guard, lock) = self.lock()?;
+        let (payloads, current_total_bytes) = &mut *data_guard;
+        if !payloads.is_empty() {
+             eprintln!("INFO: Clearing all {} payloads, freeing {} bytes.", payloads.len(), *current_total_bytes);
+             payloads.clear();
+             *current_total_bytes = 0;
+        } else {
+            eprintln!("INFO: delete_all called but no payloads to clear.");
         }
-        self.save_to_file()
+        self.save(&data_guard, &lock)
     }
 
-    /// Saves the current state (payloads only) to a file
-    fn save_to_file(&self) -> io::Result<()> {
-        let data_file = self.storage_dir.join("data.json");
-        let data_to_save = match self.data.lock() {
-            Ok(data_guard) => {
-                // Clone the payload vec and map away the size before saving
-                data_guard.0.iter()
-                    .map(|(id, value, _size)| (id.clone(), value.clone()))
-                    .collect::<Vec<_>>()
-            }
-            Err(poisoned) => {
-                eprintln!("ERROR: Storage mutex poisoned during save_to_file: {poisoned}");
-                return Err(io::Error::other("Mutex poisoned"));
-            }
-        };
+    /// Writes the current state to disk
+    pub fn flush(&self) -> io::Result<()> {
+        let (data_guard, lock) = self.lock()?;
+        self.save(&data_guard, &lock)
+    }
 
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(data_file)?;
+    /// Saves the payloads (without sizes) while the store is locked with `lock`
+    fn save(&self, data: &StorageState, lock: &File) -> io::Result<()> {
+        let data_file = self.data_file();
+        let temp_file = data_file.with_extension("json.tmp");
+        let data_to_save = data.0.iter().map(|(payload, _size)| payload).collect::<Vec<_>>();
+
+        let mut writer = io::BufWriter::new(File::create(&temp_file)?);
+        serde_json::to_writer_pretty(&mut writer, &data_to_save).map_err(io::Error::other)?;
+        writer.flush()?;
+        drop(writer);
+
+        // Replacing the file in one step means other processes never read it half-written
+        fs::rename(&temp_file, &data_file)?;
+        bump_generation(lock)?;
+        if let Ok(mut file_stamp) = self.file_stamp.lock() {
+            *file_stamp = FileStamp::of(&data_file, lock);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A store in a fresh directory of its own
+    fn storage(name: &str) -> Storage {
+        let dir = std::env::temp_dir().join(format!("dbug-storage-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Storage::in_dir(dir).unwrap()
+    }
+
+    fn values(storage: &Storage) -> Vec<Value> {
+        storage.get_all().into_iter().map(|payload| payload.value).collect()
+    }
+
+    #[test]
+    fn matching_client_id_updates_in_place() {
+        let storage = storage("update");
+        storage.add_payload(Payload::new(json!("first")).with_client_id("job")).unwrap();
+        storage.add_payload(Payload::new(json!("other"))).unwrap();
+        storage.add_payload(Payload::new(json!("second")).with_client_id("job")).unwrap();
+
+        assert_eq!(values(&storage), [json!("other"), json!("second")]);
+        let updated = &storage.get_all()[1];
+        assert!(updated.updated.is_some());
+        assert_eq!(updated.revisions.len(), 1);
+        assert_eq!(updated.revisions[0].value, json!("first"));
+        assert_eq!(updated.revisions[0].received, updated.id);
+    }
+
+    #[test]
+    fn updated_payload_moves_to_top_when_enabled() {
+        let mut storage = storage("update-top");
+        storage.set_move_updated_to_top(true);
+        storage.add_payload(Payload::new(json!("first")).with_client_id("job")).unwrap();
+        storage.add_payload(Payload::new(json!("other"))).unwrap();
+        storage.add_payload(Payload::new(json!("second")).with_client_id("job")).unwrap();
+
+        assert_eq!(values(&storage), [json!("second"), json!("other")]);
+    }
+
+    #[test]
+    fn revisions_keep_only_the_latest() {
+        let storage = storage("revisions");
+        for step in 0..MAX_REVISIONS + 5 {
+            storage.add_payload(Payload::new(json!(step)).with_client_id("job")).unwrap();
+        }
+
+        let payloads = storage.get_all();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].value, json!(MAX_REVISIONS + 4));
+        assert_eq!(payloads[0].revisions.len(), MAX_REVISIONS);
+        assert_eq!(payloads[0].revisions[0].value, json!(4));
+    }
+
+    #[test]
+    fn identical_consecutive_payloads_collapse() {
+        let storage = storage("repeats");
+        storage.add_payloads(vec![Payload::new(json!({ "a": 1 })), Payload::new(json!({ "a": 1 }))]).unwrap();
+        storage.add_payload(Payload::new(json!({ "a": 1 }))).unwrap();
+
+        let payloads = storage.get_all();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].count(), 3);
+        assert_eq!(payloads[0].last_seen.as_deref(), Some(payloads[0].received()));
+        assert_ne!(payloads[0].received(), payloads[0].id);
+    }
+
+    #[test]
+    fn only_repeats_of_the_newest_payload_collapse() {
+        let storage = storage("no-repeats");
+        storage.add_payload(Payload::new(json!("ping"))).unwrap();
+        storage.add_payload(Payload::new(json!("pong"))).unwrap();
+        storage.add_payload(Payload::new(json!("ping"))).unwrap();
+        storage.add_payload(Payload::new(json!("ping")).with_meta("level", "warn")).unwrap();
+
+        assert_eq!(storage.get_all().len(), 4);
+        assert!(storage.get_all().iter().all(|payload| payload.repeats == 0));
+    }
+
+    #[test]
+    fn payloads_with_client_ids_never_collapse() {
+        let storage = storage("client-repeats");
+        storage.add_payload(Payload::new(json!("ping")).with_client_id("a")).unwrap();
+        storage.add_payload(Payload::new(json!("ping")).with_client_id("b")).unwrap();
+        storage.add_payload(Payload::new(json!("ping"))).unwrap();
+
+        assert_eq!(storage.get_all().len(), 3);
+    }
+
+    #[test]
+    fn payloads_that_do_not_fit_beside_pinned_ones_are_dropped() {
+        let storage = storage("pinned-full");
+        let sized = |name: &str, share: u64| Payload::new(json!(name.repeat((MAX_STORAGE_BYTES / share) as usize)));
+        storage.add_payload(sized("a", 2)).unwrap();
+        let pinned_id = storage.get_all()[0].id.clone();
+        assert!(storage.toggle_pin(&pinned_id).unwrap());
+
+        // The oldest unpinned payload makes room
+        storage.add_payload(sized("b", 3)).unwrap();
+        storage.add_payload(sized("c", 3)).unwrap();
+        let kept: Vec<String> = storage.get_all().into_iter().map(|payload| payload.value.to_string()).collect();
+        assert_eq!(kept.len(), 2);
+        assert!(kept[0].starts_with("\"c") && kept[1].starts_with("\"a"));
+
+        // Nothing is evicted for a payload that would not fit anyway
+        storage.add_payload(sized("d", 2)).unwrap();
+        assert_eq!(storage.get_all().len(), 2);
+        assert!(storage.get_all()[0].value.to_string().starts_with("\"c"));
+    }
+
+    #[test]
+    fn a_pinned_payload_updated_earlier_in_a_batch_counts_at_its_new_size() {
+        let storage = storage("pinned-batch");
+        let sized = |name: &str, share: u64| Payload::new(json!(name.repeat((MAX_STORAGE_BYTES / share) as usize)));
+        storage.add_payload(sized("a", 3).with_client_id("job")).unwrap();
+        let pinned_id = storage.get_all()[0].id.clone();
+        assert!(storage.toggle_pin(&pinned_id).unwrap());
+
+        // The update keeps the old value as a revision, so the pinned payload grows to two thirds
+        let update = sized("x", 3).with_client_id("job");
+        let stored = storage.add_payloads(vec![update, sized("b", 2)]).unwrap();
+        assert_eq!(stored.len(), 1);
+        let kept = storage.get_all();
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0].pinned && kept[0].revisions.len() == 1);
+    }
+
+    #[test]
+    fn delete_all_deletes_pinned_payloads() {
+        let storage = storage("clear-pinned");
+        storage.add_payload(Payload::new(json!("keep?"))).unwrap();
+        let id = storage.get_all()[0].id.clone();
+        storage.toggle_pin(&id).unwrap();
+        storage.delete_all().unwrap();
+
+        assert!(storage.get_all().is_empty());
+    }
+
+    #[test]
+    fn new_ids_stay_unique_when_an_update_moves_an_older_payload_last() {
+        let mut storage = storage("unique-ids");
+        storage.set_move_updated_to_top(true);
+        let mut first = Payload::new(json!("first")).with_client_id("job");
+        first.id = "100".to_string();
+        let mut second = Payload::new(json!("second"));
+        second.id = "200".to_string();
+        storage.add_payloads(vec![first, second]).unwrap();
+
+        // The update keeps ID 100 but is now the last payload
+        let mut update = Payload::new(json!("first again")).with_client_id("job");
+        update.id = "150".to_string();
+        let mut same_millisecond = Payload::new(json!("third"));
+        same_millisecond.id = "200".to_string();
+        storage.add_payloads(vec![update, same_millisecond]).unwrap();
+
+        let ids: Vec<String> = storage.get_all().into_iter().map(|payload| payload.id).collect();
+        assert_eq!(ids[1..], ["100", "200"]);
+        assert!(ids[0].parse::<i64>().unwrap() > 200, "{ids:?}");
+    }
+
+    #[test]
+    fn add_payloads_returns_payloads_as_stored() {
+        let storage = storage("returned");
+        let payload = Payload::new(json!("same millisecond"));
+        let mut twin = Payload::new(json!("twin"));
+        twin.id.clone_from(&payload.id);
+        let repeat = Payload::new(json!("twin"));
+
+        let returned = storage.add_payloads(vec![payload, twin, repeat]).unwrap();
+        let stored = storage.get_all();
+        assert_eq!(returned.len(), 3);
+        assert_eq!(returned[1].id, stored[0].id);
+        assert_ne!(returned[0].id, returned[1].id);
+        assert_eq!(returned[2].count(), 2);
+    }
+
+    #[test]
+    fn subscribers_receive_payloads_with_their_final_ids() {
+        let storage = storage("subscribe");
+        let mut receiver = subscribe();
+        let first = Payload::new(json!("subscribed"));
+        let mut twin = Payload::new(json!("subscribed twin"));
+        twin.id.clone_from(&first.id);
+        storage.add_payloads(vec![first, twin]).unwrap();
+
+        // Other tests store payloads of their own meanwhile
+        let received: Vec<Payload> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .filter(|payload| payload.value.as_str().is_some_and(|value| value.starts_with("subscribed")))
+            .collect();
+        let stored = storage.get_all();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].id, stored[0].id);
+        assert_eq!(received[0].id, stored[1].id);
+    }
+
+    #[test]
+    fn delete_by_client_id_removes_every_match_in_one_save() {
+        let storage = storage("delete-client");
+        let mut twin = Payload::new(json!("two")).with_client_id("job");
+        twin.id = "1".to_string();
+        storage.add_payload(Payload::new(json!("one")).with_client_id("job")).unwrap();
+        storage.add_payload(Payload::new(json!("other"))).unwrap();
+        // Updates in place leave one payload per client ID, so a second one is only found in a loaded file
+        storage.data.lock().unwrap().0.push((twin, 10));
+        let lock = Storage::lock_file(storage.dir()).unwrap();
+        let generation = read_generation(&lock);
+
+        assert!(storage.delete_by_client_id("job").unwrap());
+        assert_eq!(values(&storage), [json!("other")]);
+        assert_eq!(read_generation(&lock), generation + 1);
+        assert!(!storage.delete_by_client_id("job").unwrap());
+    }
+
+    #[tokio::test]
+    async fn watch_yields_when_another_store_saves() {
+        let watched = storage("watch");
+        let mut changes = Box::pin(watch(watched.dir().to_path_buf()));
+        let writer = Storage::in_dir(watched.dir()).unwrap();
+        writer.add_payload(Payload::new(json!("from elsewhere"))).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), changes.next())
+            .await
+            .expect("no change was reported");
+        assert!(watched.reload_if_changed().unwrap());
+        assert_eq!(values(&watched), [json!("from elsewhere")]);
+    }
+
+    #[test]
+    fn reload_picks_up_saves_by_another_store() {
+        let writer = storage("shared");
+        let reader = Storage::in_dir(&writer.storage_dir).unwrap();
+        writer.add_payload(Payload::with_id("1".to_string(), json!("a"))).unwrap();
+        assert!(reader.reload_if_changed().unwrap());
+        assert_eq!(values(&reader), [json!("a")]);
+        assert!(!reader.reload_if_changed().unwrap());
 
-        let writer = io::BufWriter::new(file);
+        // A save the timestamp and length alone would not reveal
+        let data_file = writer.data_file();
+        let before = fs::metadata(&data_file).unwrap();
+        writer.delete_all().unwrap();
+        writer.add_payload(Payload::with_id("1".to_string(), json!("b"))).unwrap();
+        File::options().write(true).open(&data_file).unwrap().set_modified(before.modified().unwrap()).unwrap();
+        assert_eq!(fs::metadata(&data_file).unwrap().len(), before.len());
 
-        // Serialize the mapped data (without sizes)
-        serde_json::to_writer_pretty(writer, &data_to_save)
-            .map_err(io::Error::other)
+        assert!(reader.reload_if_changed().unwrap());
+        assert_eq!(values(&reader), [json!("b")]);
     }
 }
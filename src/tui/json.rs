@@ -0,0 +1,114 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// The kinds of text on a line of pretty-printed JSON, colored like `highlight_json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` and `null`
+    Literal,
+    Bracket,
+    Punctuation,
+    Whitespace,
+}
+
+impl Token {
+    fn style(self) -> Style {
+        match self {
+            Self::Key => Style::new().fg(Color::Cyan),
+            Self::String => Style::new().fg(Color::Green),
+            Self::Number => Style::new().fg(Color::Yellow),
+            Self::Literal => Style::new().fg(Color::Magenta),
+            Self::Bracket | Self::Punctuation => Style::new().fg(Color::DarkGray),
+            Self::Whitespace => Style::new(),
+        }
+    }
+}
+
+/// Splits a line of pretty-printed JSON into tokens, keeping every character
+fn tokenize(line: &str) -> Vec<(&str, Token)> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    while let Some(first) = rest.chars().next() {
+        let (len, token) = match first {
+            '"' => {
+                // Find the closing quote, skipping escaped characters
+                let mut escaped = false;
+                let end = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|&(_, c)| {
+                        let closes = c == '"' && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        closes
+                    })
+                    .map_or(rest.len(), |(idx, _)| idx + 1);
+                let is_key = rest[end..].trim_start().starts_with(':');
+                (end, if is_key { Token::Key } else { Token::String })
+            }
+            '{' | '}' | '[' | ']' => (1, Token::Bracket),
+            ':' | ',' => (1, Token::Punctuation),
+            c if c.is_whitespace() => (
+                rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len()),
+                Token::Whitespace,
+            ),
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}'))
+                    .unwrap_or(rest.len());
+                let token = if rest[..end].parse::<f64>().is_ok() {
+                    Token::Number
+                } else {
+                    Token::Literal
+                };
+                (end, token)
+            }
+        };
+        tokens.push((&rest[..len], token));
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Splits `text` into spans, marking case-insensitive matches of `query`
+fn spans_with_matches(text: &str, style: Style, query: &str) -> Vec<Span<'static>> {
+    if query.is_empty() {
+        return vec![Span::styled(text.to_string(), style)];
+    }
+    // ASCII lowercasing keeps byte offsets valid for slicing `text`
+    let haystack = text.to_ascii_lowercase();
+    let needle = query.to_ascii_lowercase();
+    let match_style = style.bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD);
+
+    let mut spans = Vec::new();
+    let mut current = 0;
+    for (start, matched) in haystack.match_indices(&needle) {
+        if start > current {
+            spans.push(Span::styled(text[current..start].to_string(), style));
+        }
+        let end = start + matched.len();
+        spans.push(Span::styled(text[start..end].to_string(), match_style));
+        current = end;
+    }
+    if current < text.len() {
+        spans.push(Span::styled(text[current..].to_string(), style));
+    }
+    spans
+}
+
+/// Colors pretty-printed JSON line by line, with line numbers and search matches highlighted
+pub(super) fn highlight(json: &str, search_query: &str) -> Vec<Line<'static>> {
+    let line_number_style = Style::new().fg(Color::DarkGray);
+    json.lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            let mut spans = vec![Span::styled(format!("{:>4} ", idx + 1), line_number_style)];
+            for (text, token) in tokenize(line) {
+                spans.extend(spans_with_matches(text, token.style(), search_query));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
@@ -0,0 +1,404 @@
+mod json;
+mod view;
+
+use crate::components::payload_json;
+use crate::instance;
+use crate::keymap::{self, KeyAction};
+use dbug::server::{self, ServerMessage};
+use dbug::settings::{NewPayloadBehavior, Settings};
+use dbug::storage::{self, Payload, Storage};
+use futures::StreamExt;
+use iced::keyboard::{key, Key, Modifiers};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::widgets::ListState;
+use ratatui::DefaultTerminal;
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+// How long to wait for a key press before checking for new payloads
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Lines scrolled by Page Up and Page Down in an expanded payload
+const PAGE_LINES: usize = 10;
+
+/// The terminal front-end's state, the counterpart of `App`
+struct Tui {
+    storage: Storage,
+    settings: Settings,
+    payloads: Vec<Payload>,
+    /// Indices into `payloads` matching the search query, newest first
+    visible: Vec<usize>,
+    list_state: ListState,
+    selected_payload_id: Option<String>,
+    expanded_payload_id: Option<String>,
+    /// Lines of the expanded payload's JSON scrolled past
+    json_scroll: usize,
+    search_query: String,
+    /// Whether typed characters go to the search query
+    searching: bool,
+    /// Shown in the footer until the next key press
+    status: Option<String>,
+    rejected_requests: usize,
+    server_running: bool,
+    quit: bool,
+}
+
+/// Runs the server and shows its payloads in the terminal until `q` or Ctrl+C
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::load();
+    let mut storage = Storage::new()?;
+    storage.set_move_updated_to_top(settings.is_move_updated_to_top());
+
+    // Server and storage diagnostics would be written over the screen
+    #[cfg(unix)]
+    let _stderr = StderrRedirect::to_file(&Storage::config_path().with_file_name("tui.log"));
+
+    // The server runs on the runtime; the terminal is drawn from this thread
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (sender, messages) = mpsc::channel();
+    runtime.spawn(async move {
+        let mut stream = Box::pin(futures::stream::select(server::listen(), instance::imports()));
+        while let Some(message) = stream.next().await {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let (sender, changes) = mpsc::channel();
+    runtime.spawn({
+        let storage_dir = storage.dir().to_path_buf();
+        async move {
+            let mut stream = Box::pin(storage::watch(storage_dir));
+            while stream.next().await.is_some() {
+                if sender.send(()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut tui = Tui::new(storage, settings);
+    let mut terminal = ratatui::try_init()?;
+    let result = tui.run(&mut terminal, &messages, &changes);
+    ratatui::restore();
+    tui.storage.flush()?;
+    Ok(result?)
+}
+
+/// Sends stderr to a file while the TUI owns the terminal, restoring it when dropped
+#[cfg(unix)]
+struct StderrRedirect {
+    saved: std::os::fd::RawFd,
+}
+
+#[cfg(unix)]
+impl StderrRedirect {
+    /// Redirects only when stderr is the terminal, so `2>file` keeps working
+    fn to_file(path: &std::path::Path) -> Option<Self> {
+        use std::io::IsTerminal;
+        use std::os::fd::AsRawFd;
+
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path).ok()?;
+        // SAFETY: only the process's own stderr descriptor is duplicated and replaced
+        unsafe {
+            let saved = libc::dup(libc::STDERR_FILENO);
+            if saved < 0 {
+                return None;
+            }
+            if libc::dup2(file.as_raw_fd(), libc::STDERR_FILENO) < 0 {
+                libc::close(saved);
+                return None;
+            }
+            Some(Self { saved })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StderrRedirect {
+    fn drop(&mut self) {
+        // SAFETY: `saved` is the descriptor duplicated from stderr in `to_file`
+        unsafe {
+            libc::dup2(self.saved, libc::STDERR_FILENO);
+            libc::close(self.saved);
+        }
+    }
+}
+
+impl Tui {
+    fn new(storage: Storage, settings: Settings) -> Self {
+        let mut tui = Self {
+            storage,
+            settings,
+            payloads: Vec::new(),
+            visible: Vec::new(),
+            list_state: ListState::default(),
+            selected_payload_id: None,
+            expanded_payload_id: None,
+            json_scroll: 0,
+            search_query: String::new(),
+            searching: false,
+            status: None,
+            rejected_requests: 0,
+            server_running: true,
+            quit: false,
+        };
+        tui.refresh_payloads();
+        tui
+    }
+
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        messages: &Receiver<ServerMessage>,
+        changes: &Receiver<()>,
+    ) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| view::draw(frame, self))?;
+
+            if event::poll(POLL_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    // Ctrl+L repaints the screen, e.g. after log lines were written over it
+                    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('l') {
+                        terminal.clear()?;
+                    } else if key.kind == KeyEventKind::Press {
+                        self.handle_key(key);
+                    }
+                }
+            }
+
+            loop {
+                match messages.try_recv() {
+                    Ok(message) => self.receive(message),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.server_running = false;
+                        break;
+                    }
+                }
+            }
+
+            // Another process, e.g. `dbug send` run while the server was down, changed the stored payloads
+            if changes.try_iter().count() > 0 {
+                match self.storage.reload_if_changed() {
+                    Ok(true) => self.refresh_payloads(),
+                    Ok(false) => {}
+                    Err(e) => self.status = Some(format!("Failed to check for stored changes: {e}")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores a server message and updates the list like the window does
+    fn receive(&mut self, message: ServerMessage) {
+        if let ServerMessage::RequestRejected(reason) = &message {
+            self.rejected_requests += 1;
+            self.status = Some(format!("Rejected request: {reason}"));
+            return;
+        }
+
+        let new_payloads = message.payloads();
+        // Payloads updated in place keep the selection unless they move to the top
+        let updates_in_place = !self.settings.is_move_updated_to_top()
+            && !new_payloads.is_empty()
+            && new_payloads.iter().all(|payload| self.is_stored_client_id(payload));
+        if let Err(e) = message.store(&self.storage) {
+            self.status = Some(format!("Failed to store payload: {e}"));
+        }
+        self.refresh_payloads();
+
+        if new_payloads.is_empty() || updates_in_place {
+            return;
+        }
+        let newest = self.payloads.first().map(|payload| payload.id.clone());
+        match self.settings.get_new_payload_behavior() {
+            NewPayloadBehavior::ExpandNewest => self.expand(newest),
+            NewPayloadBehavior::ExpandIfNone if self.expanded_payload_id.is_none() => self.expand(newest),
+            NewPayloadBehavior::KeepSelection | NewPayloadBehavior::ExpandIfNone => {}
+        }
+    }
+
+    fn is_stored_client_id(&self, payload: &Payload) -> bool {
+        payload.client_id.as_ref().is_some_and(|client_id| {
+            self.payloads
+                .iter()
+                .any(|stored| stored.client_id.as_ref() == Some(client_id))
+        })
+    }
+
+    /// Reloads payloads from storage, dropping a selection that no longer exists
+    fn refresh_payloads(&mut self) {
+        self.payloads = self.storage.get_all();
+        if !self.is_stored_id(self.expanded_payload_id.as_deref()) {
+            self.expanded_payload_id = None;
+        }
+        self.apply_search();
+    }
+
+    fn is_stored_id(&self, id: Option<&str>) -> bool {
+        id.is_some_and(|id| self.payloads.iter().any(|payload| payload.id == id))
+    }
+
+    /// Filters the list by the search query and keeps the selection on a visible payload
+    fn apply_search(&mut self) {
+        let query = self.search_query.to_lowercase();
+        self.visible = self
+            .payloads
+            .iter()
+            .enumerate()
+            .filter(|(_, payload)| query.is_empty() || payload_json(payload).to_lowercase().contains(&query))
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let selected = self.selected_index().or_else(|| (!self.visible.is_empty()).then_some(0));
+        self.select_index(selected);
+    }
+
+    /// Position of the selected payload among the visible ones
+    fn selected_index(&self) -> Option<usize> {
+        let id = self.selected_payload_id.as_ref()?;
+        self.visible.iter().position(|&idx| &self.payloads[idx].id == id)
+    }
+
+    fn select_index(&mut self, position: Option<usize>) {
+        let position = position.filter(|&position| position < self.visible.len());
+        let id = position.map(|position| self.payloads[self.visible[position]].id.clone());
+        if id != self.selected_payload_id {
+            self.json_scroll = 0;
+        }
+        self.selected_payload_id = id;
+        self.list_state.select(position);
+    }
+
+    fn expand(&mut self, id: Option<String>) {
+        self.json_scroll = 0;
+        self.expanded_payload_id.clone_from(&id);
+        let position = id.and_then(|id| self.visible.iter().position(|&idx| self.payloads[idx].id == id));
+        if position.is_some() {
+            self.select_index(position);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        self.status = None;
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        if self.searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search_query.clear();
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Backspace => {
+                    self.search_query.pop();
+                }
+                KeyCode::Char(c) => self.search_query.push(c),
+                _ => return,
+            }
+            self.apply_search();
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::PageDown => self.json_scroll += PAGE_LINES,
+            KeyCode::PageUp => self.json_scroll = self.json_scroll.saturating_sub(PAGE_LINES),
+            _ => {
+                let Some((key, modifiers)) = iced_key(key) else {
+                    return;
+                };
+                if let Some(action) = keymap::action_for(&key, modifiers) {
+                    self.perform_key_action(action);
+                }
+            }
+        }
+    }
+
+    /// Performs an action from the window's keymap, so both front-ends share their shortcuts
+    fn perform_key_action(&mut self, action: KeyAction) {
+        let last = self.visible.len().checked_sub(1);
+        match action {
+            KeyAction::SelectPrevious => {
+                let position = self.selected_index().map_or(0, |position| position.saturating_sub(1));
+                self.select_index(Some(position));
+            }
+            KeyAction::SelectNext => {
+                let position = self.selected_index().map_or(0, |position| position + 1);
+                self.select_index(Some(position.min(last.unwrap_or_default())));
+            }
+            KeyAction::SelectFirst => self.select_index(Some(0)),
+            KeyAction::SelectLast => self.select_index(last),
+            KeyAction::ToggleExpanded => {
+                let expanded = self.expanded_payload_id.is_some() && self.expanded_payload_id == self.selected_payload_id;
+                self.expand(if expanded { None } else { self.selected_payload_id.clone() });
+            }
+            KeyAction::Delete => {
+                let Some(id) = self.selected_payload_id.clone() else {
+                    return;
+                };
+                let position = self.selected_index();
+                if let Err(e) = self.storage.delete(&id) {
+                    self.status = Some(format!("Failed to delete payload: {e}"));
+                }
+                self.selected_payload_id = None;
+                self.refresh_payloads();
+                self.select_index(position.map(|position| position.min(self.visible.len().saturating_sub(1))));
+            }
+            KeyAction::TogglePin => {
+                let Some(id) = self.selected_payload_id.clone() else {
+                    return;
+                };
+                match self.storage.toggle_pin(&id) {
+                    Ok(true) => self.status = Some("Pinned; kept when storage is full".to_string()),
+                    Ok(false) => self.status = Some("Unpinned".to_string()),
+                    Err(e) => self.status = Some(format!("Failed to pin payload: {e}")),
+                }
+                self.refresh_payloads();
+            }
+            KeyAction::Search => self.searching = true,
+            KeyAction::CloseModal if !self.search_query.is_empty() => {
+                self.search_query.clear();
+                self.apply_search();
+            }
+            KeyAction::CloseModal => self.expand(None),
+            KeyAction::Copy | KeyAction::OpenSettings | KeyAction::FocusNext | KeyAction::FocusPrevious => {
+                self.status = Some("Only available in the window".to_string());
+            }
+        }
+    }
+}
+
+/// Translates a terminal key press into the key and modifiers the keymap matches against
+fn iced_key(event: KeyEvent) -> Option<(Key, Modifiers)> {
+    let mut modifiers = Modifiers::empty();
+    if event.modifiers.contains(KeyModifiers::SHIFT) {
+        modifiers |= Modifiers::SHIFT;
+    }
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        modifiers |= Modifiers::COMMAND;
+    }
+    let key = match event.code {
+        KeyCode::Char(c) => Key::Character(c.to_string().into()),
+        KeyCode::Up => Key::Named(key::Named::ArrowUp),
+        KeyCode::Down => Key::Named(key::Named::ArrowDown),
+        KeyCode::Enter => Key::Named(key::Named::Enter),
+        KeyCode::Esc => Key::Named(key::Named::Escape),
+        KeyCode::Delete => Key::Named(key::Named::Delete),
+        KeyCode::Tab => Key::Named(key::Named::Tab),
+        KeyCode::BackTab => {
+            modifiers |= Modifiers::SHIFT;
+            Key::Named(key::Named::Tab)
+        }
+        _ => return None,
+    };
+    Some((key, modifiers))
+}
@@ -0,0 +1,131 @@
+use super::{json, Tui};
+use crate::components::payload_json;
+use dbug::storage::Payload;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{HighlightSpacing, List, ListItem, Paragraph};
+use ratatui::Frame;
+
+const KEY_HINTS: &str = "↑↓/jk select · Enter expand · PgUp/PgDn scroll · / search · d delete · p pin · q quit";
+
+/// Draws the header, the payload list and the footer
+pub(super) fn draw(frame: &mut Frame, tui: &mut Tui) {
+    let [header_area, list_area, footer_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+
+    frame.render_widget(header(tui), header_area);
+
+    // The expanded payload's JSON gets every row the list has, minus its own header line
+    let json_rows = usize::from(list_area.height.saturating_sub(1));
+    let preview_width = usize::from(list_area.width).saturating_sub(2);
+    let items: Vec<ListItem> = tui
+        .visible
+        .iter()
+        .map(|&idx| {
+            let payload = &tui.payloads[idx];
+            let expanded = tui.expanded_payload_id.as_ref() == Some(&payload.id);
+            let mut lines = vec![summary_line(payload, expanded, preview_width)];
+            if expanded {
+                let json_lines = json::highlight(&payload_json(payload), &tui.search_query);
+                tui.json_scroll = tui.json_scroll.min(json_lines.len().saturating_sub(1));
+                lines.extend(json_lines.into_iter().skip(tui.json_scroll).take(json_rows));
+            }
+            ListItem::new(Text::from(lines))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .highlight_symbol("▌ ")
+        .highlight_spacing(HighlightSpacing::Always)
+        .highlight_style(Style::new().add_modifier(Modifier::BOLD));
+    frame.render_stateful_widget(list, list_area, &mut tui.list_state);
+
+    if tui.visible.is_empty() {
+        let empty = if tui.search_query.is_empty() {
+            "Waiting for payloads…"
+        } else {
+            "No payloads match the search"
+        };
+        frame.render_widget(Paragraph::new(empty).style(Style::new().fg(Color::DarkGray)), list_area);
+    }
+
+    frame.render_widget(footer(tui), footer_area);
+}
+
+fn header(tui: &Tui) -> Line<'static> {
+    let dim = Style::new().fg(Color::DarkGray);
+    let mut spans = vec![
+        Span::styled(" dbug ", Style::new().add_modifier(Modifier::BOLD)),
+        Span::styled(
+            format!("http://{}:{}", tui.settings.get_server_host(), tui.settings.get_server_port()),
+            dim,
+        ),
+        Span::styled(format!(" · {} payloads", tui.payloads.len()), dim),
+    ];
+    if tui.visible.len() != tui.payloads.len() {
+        spans.push(Span::styled(format!(", {} shown", tui.visible.len()), dim));
+    }
+    if tui.rejected_requests > 0 {
+        spans.push(Span::styled(format!(" · {} rejected", tui.rejected_requests), Style::new().fg(Color::Red)));
+    }
+    if !tui.server_running {
+        spans.push(Span::styled(" · server stopped", Style::new().fg(Color::Red)));
+    }
+    Line::from(spans)
+}
+
+fn footer(tui: &Tui) -> Line<'static> {
+    if tui.searching {
+        return Line::from(vec![
+            Span::raw(format!("/{}", tui.search_query)),
+            Span::styled("▏", Style::new().add_modifier(Modifier::SLOW_BLINK)),
+            Span::styled("  Enter keep · Esc clear", Style::new().fg(Color::DarkGray)),
+        ]);
+    }
+    match &tui.status {
+        Some(status) => Line::raw(format!(" {status}")),
+        None if !tui.search_query.is_empty() => Line::styled(
+            format!(" Search: {} · Esc clear · {KEY_HINTS}", tui.search_query),
+            Style::new().fg(Color::DarkGray),
+        ),
+        None => Line::styled(format!(" {KEY_HINTS}"), Style::new().fg(Color::DarkGray)),
+    }
+}
+
+/// One line per payload: time, badges and as much of the value as fits
+fn summary_line(payload: &Payload, expanded: bool, width: usize) -> Line<'static> {
+    let dim = Style::new().fg(Color::DarkGray);
+    let time = payload
+        .received()
+        .parse::<i64>()
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|time| time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    let mut spans = vec![
+        Span::styled(if expanded { "▾ " } else { "▸ " }, dim),
+        Span::styled(format!("{time} "), dim),
+    ];
+    if payload.pinned {
+        spans.push(Span::styled("● ", Style::new().fg(Color::Yellow)));
+    }
+    if let Some(kind) = payload.meta.get("kind").and_then(|kind| kind.as_str()) {
+        spans.push(Span::styled(format!("{kind} "), Style::new().fg(Color::Blue)));
+    }
+    if payload.last_seen.is_some() {
+        spans.push(Span::styled(format!("×{} ", payload.count()), Style::new().fg(Color::Magenta)));
+    }
+    if !payload.revisions.is_empty() {
+        spans.push(Span::styled(format!("rev {} ", payload.revisions.len() + 1), dim));
+    }
+
+    let used: usize = spans.iter().map(Span::width).sum();
+    let mut preview = payload.document().to_string();
+    if let Some((idx, _)) = preview.char_indices().nth(width.saturating_sub(used)) {
+        preview.truncate(idx);
+    }
+    spans.push(Span::raw(preview));
+    Line::from(spans)
+}
@@ -0,0 +1,106 @@
+use dbug::settings::UpstreamRoute;
+use dbug::{server, Settings, Storage};
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// A port nothing listens on, for the dbug server under test
+fn free_port() -> u16 {
+    std::net::TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("no free port")
+}
+
+/// Posts `body` to the dbug server, retrying while it is still starting
+async fn post(url: &str, body: &Value) -> reqwest::Response {
+    let client = reqwest::Client::new();
+    for _ in 0..50 {
+        match client.post(url).body(body.to_string()).send().await {
+            Ok(response) => return response,
+            Err(e) if e.is_connect() => tokio::time::sleep(Duration::from_millis(100)).await,
+            Err(e) => panic!("request to {url} failed: {e}"),
+        }
+    }
+    panic!("dbug server at {url} did not start");
+}
+
+/// Starts a dbug server forwarding `/proxy` to `upstream`, storing what it receives in a fresh store
+///
+/// Returns the server's port, the store and a stream that yields each time a message was stored.
+fn start_dbug(name: &str, upstream: &str) -> (u16, Storage, UnboundedReceiver<()>) {
+    let port = free_port();
+    let mut settings = Settings::default();
+    settings.set_server_port(port);
+    settings.set_upstream_routes(vec![UpstreamRoute {
+        path: "/proxy".to_string(),
+        url: upstream.to_string(),
+    }]);
+
+    let dir = std::env::temp_dir().join(format!("dbug-proxy-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let storage = Storage::in_dir(&dir).expect("failed to open storage");
+    let (stored, stored_rx) = futures::channel::mpsc::unbounded();
+    tokio::spawn({
+        let storage = storage.clone();
+        async move {
+            let mut messages = Box::pin(server::listen_with(settings));
+            while let Some(message) = messages.next().await {
+                message.store(&storage).expect("failed to store message");
+                let _ = stored.unbounded_send(());
+            }
+        }
+    });
+    (port, storage, stored_rx)
+}
+
+#[tokio::test]
+async fn proxy_relays_upstream_response_and_stores_the_exchange() {
+    let upstream = warp::post()
+        .and(warp::path!("api" / "users"))
+        .and(warp::body::json())
+        .map(|user: Value| {
+            warp::reply::with_status(warp::reply::json(&json!({ "created": user })), StatusCode::CREATED)
+        });
+    let (upstream_addr, upstream_server) = warp::serve(upstream).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(upstream_server);
+    let (port, storage, mut stored_rx) = start_dbug("relay", &format!("http://{upstream_addr}/api"));
+
+    let response = post(&format!("http://127.0.0.1:{port}/proxy/users"), &json!({ "name": "Ada" })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let body = response.bytes().await.expect("failed to read relayed body");
+    let body: Value = serde_json::from_slice(&body).expect("relayed body is not JSON");
+    assert_eq!(body, json!({ "created": { "name": "Ada" } }));
+
+    tokio::time::timeout(Duration::from_secs(5), stored_rx.next())
+        .await
+        .expect("exchange was not stored");
+    let payloads = storage.get_all();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].value, json!({ "name": "Ada" }));
+    assert_eq!(
+        payloads[0].response,
+        Some(json!({ "status": 201, "body": { "created": { "name": "Ada" } } }))
+    );
+}
+
+#[tokio::test]
+async fn proxy_passes_the_query_string_upstream() {
+    let upstream = warp::post()
+        .and(warp::path!("api" / "1" / "store"))
+        .and(warp::query::raw())
+        .map(|query: String| warp::reply::json(&json!({ "query": query })));
+    let (upstream_addr, upstream_server) = warp::serve(upstream).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(upstream_server);
+    let (port, _storage, _stored_rx) = start_dbug("query", &format!("http://{upstream_addr}/api"));
+
+    let url = format!("http://127.0.0.1:{port}/proxy/1/store?sentry_key=abc&sentry_version=7");
+    let response = post(&url, &json!({ "message": "boom" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.bytes().await.expect("failed to read relayed body");
+    let body: Value = serde_json::from_slice(&body).expect("relayed body is not JSON");
+    assert_eq!(body, json!({ "query": "sentry_key=abc&sentry_version=7" }));
+}